@@ -3,44 +3,26 @@ use comui::{
     components::{button::QuadButton, label::Label},
     input::subscriber_id,
     layout::{Layout, LayoutBuilder},
+    material::{MaterialShading, load_material},
     shading::IntoShading,
     utils::Transform,
     window::Window,
 };
-use lyon::{
-    geom::traits::Transformation,
-    math::Box2D,
-    path::{Path, Winding, builder::BorderRadii},
-};
+use lyon::{math::Box2D, path::builder::BorderRadii};
 use macroquad::{
     color::{self, Color, WHITE},
     input::{
         MouseButton, Touch, TouchPhase, is_mouse_button_down, mouse_position,
         utils::repeat_all_miniquad_input,
     },
-    math::vec2,
+    material::Material,
+    math::{Rect, vec2},
     miniquad::EventHandler,
     prelude::info,
     window::{clear_background, next_frame, screen_dpi_scale},
 };
 use nalgebra::Matrix3;
 
-struct MyTransfrom(pub Transform);
-
-impl Transformation<f32> for MyTransfrom {
-    fn transform_point(&self, p: lyon::geom::Point<f32>) -> lyon::geom::Point<f32> {
-        let p = nalgebra::Point2::new(p.x, p.y);
-        let p = self.0.transform_point(&p);
-        lyon::geom::Point::new(p.x, p.y)
-    }
-
-    fn transform_vector(&self, v: lyon::geom::Vector<f32>) -> lyon::geom::Vector<f32> {
-        let v = nalgebra::Vector2::new(v.x, v.y);
-        let v = self.0.transform_vector(&v);
-        lyon::geom::Vector::new(v.x, v.y)
-    }
-}
-
 #[derive(Default)]
 enum Colors {
     #[default]
@@ -107,28 +89,40 @@ impl Layout for MyFancyBtn {
                 const C3: f32 = C1 + 1.0;
                 1.0 + C3 * (t - 1.0).powi(3) + C1 * (t - 1.0).powi(2)
             };
-        let path = {
-            let mut builder = Path::builder();
-            builder.add_rounded_rectangle(
-                &Box2D::new(
-                    lyon::math::point(-0.5 * size, -0.5 * size),
-                    lyon::math::point(0.5 * size, 0.5 * size),
-                ),
-                &BorderRadii::new(self.radius),
-                Winding::Positive,
-            );
-            builder.build()
-        };
-        target.fill_path(
-            &path.transformed(&MyTransfrom(*tr)),
+        target.fill_rounded_rect(
+            &Box2D::new(
+                lyon::math::point(-0.5 * size, -0.5 * size),
+                lyon::math::point(0.5 * size, 0.5 * size),
+            ),
+            &BorderRadii::new(self.radius),
+            tr,
             self.color.color().into_shading(),
             1.0,
         );
     }
 }
 
+/// Animated stripes behind the button
+const BACKGROUND: &str = r#"#version 100
+precision mediump float;
+
+varying lowp vec2 uv;
+varying lowp vec4 color;
+
+uniform float Time;
+uniform vec2 LocalSize;
+
+void main() {
+    float stripe = sin((uv.x * LocalSize.x + uv.y * LocalSize.y) / 8.0 - Time * 4.0);
+    vec4 result = mix(vec4(1.0, 0.8, 0.3, 1.0), vec4(1.0, 0.5, 0.6, 1.0), stripe * 0.5 + 0.5);
+    result.a *= color.a;
+    gl_FragColor = vec4(result.rgb * result.a, result.a);
+}"#;
+
 struct Main {
     clicked: bool,
+    /// `None` if the shader failed to compile
+    background: Option<Material>,
     fancy_btn: MyFancyBtn,
     label: Label,
 }
@@ -137,6 +131,9 @@ impl Default for Main {
     fn default() -> Self {
         Self {
             clicked: false,
+            background: load_material(BACKGROUND, Vec::new())
+                .inspect_err(|e| info!("Failed to load the background: {:?}", e))
+                .ok(),
             fancy_btn: MyFancyBtn::default(),
             label: Label::new("A FANCY Button, 😲这么强？！")
                 .with_font_size(20.0)
@@ -161,12 +158,22 @@ impl Layout for Main {
             .build()
     }
 
-    fn before_render(&mut self, _tr: &Transform, _target: &mut Window) {
+    fn before_render(&mut self, tr: &Transform, target: &mut Window) {
         if self.fancy_btn.inner.triggered {
             self.clicked = !self.clicked;
             self.fancy_btn.inner.triggered = false;
             self.fancy_btn.color.next();
         }
+        if let Some(material) = &self.background {
+            let x = 40.0 + if self.clicked { 150.0 } else { 0.0 };
+            let rect = Box2D::new(
+                lyon::math::point(x, 40.0),
+                lyon::math::point(x + 120.0, 160.0),
+            );
+            let shading =
+                MaterialShading::new(material.clone(), tr, Rect::new(x, 40.0, 120.0, 120.0));
+            target.fill_rounded_rect(&rect, &BorderRadii::new(16.0), tr, shading, 1.0);
+        }
     }
 }
 
@@ -199,6 +206,7 @@ impl EventHandler for Handler {
         }
     }
 
+    // Event positions are physical, unlike `mouse_position`
     fn mouse_button_down_event(&mut self, button: MouseButton, x: f32, y: f32) {
         self.touches.push(Touch {
             id: button_to_id(button),
@@ -229,6 +237,7 @@ fn config() -> macroquad::prelude::Conf {
 async fn main() {
     let mut handler = Handler::default();
     let mut main_view = Main::default();
+    let mut window = Window::default();
     loop {
         clear_background(WHITE);
         handler.update();
@@ -238,7 +247,10 @@ async fn main() {
                 info!("Error handling touch: {:?}", e);
             }
         }
-        main_view.render(&Matrix3::identity(), &mut Window::default());
+        main_view.render(&Matrix3::identity(), &mut window);
+        if let Some(metrics) = window.update() {
+            main_view.resize(&metrics);
+        }
         next_frame().await
     }
 }
@@ -23,7 +23,8 @@ impl Default for Main {
                 .with_texture_align((0.5, 0.0))
                 .with_font_size(20.)
                 .with_line_height(25.),
-            label_centered: Label::new("⬆️上面左对齐\n中间↔️中心对齐\n下面右对齐⬇️")
+            label_centered: Label::default()
+                .with_markup("⬆️<b>上面</b>左对齐\n中间↔️<color=#ff0>中心</color>对齐\n下面<size=40>右对齐</size>⬇️")
                 .with_texture_align((0.5, 0.0))
                 .with_align(Align::Center)
                 .with_font_size(60.)
@@ -64,7 +65,7 @@ impl Layout for Main {
 
     fn before_render(&mut self, _tr: &comui::utils::Transform, target: &mut Window) {
         let percent = (get_time() % 10. / 10.) * 0.85 + 0.15;
-        let width = Some(target.pixel_width as f32 * percent as f32 - 20.0);
+        let width = Some(target.logical_size().0 * percent as f32 - 20.0);
         self.label_left.area_width = width;
         self.label_centered.area_width = width;
         self.label_right.area_width = width;
@@ -94,7 +95,9 @@ async fn main() {
     loop {
         clear_background(BLUE);
         main_view.render(&Matrix3::identity(), &mut window);
-        window.update();
+        if let Some(metrics) = window.update() {
+            main_view.resize(&metrics);
+        }
         tracing::event!(
             tracing::Level::INFO,
             message = "finished frame",
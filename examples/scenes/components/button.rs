@@ -6,14 +6,11 @@ use comui::{
     utils::Transform,
     window::Window,
 };
-use lyon::{
-    math::Box2D,
-    path::{Path, Winding, builder::BorderRadii},
-};
+use lyon::{math::Box2D, path::builder::BorderRadii};
 use macroquad::color::{self, Color};
 
-use crate::utils::MyTransfrom;
-
+// Only gray is used by the example so far
+#[allow(dead_code)]
 #[derive(Default)]
 pub enum Colors {
     #[default]
@@ -23,6 +20,7 @@ pub enum Colors {
     Blue,
 }
 
+#[allow(dead_code)]
 impl Colors {
     fn color(&self) -> Color {
         match self {
@@ -81,20 +79,13 @@ impl Layout for MyFancyBtn {
                 const C3: f32 = C1 + 1.0;
                 1.0 + C3 * (t - 1.0).powi(3) + C1 * (t - 1.0).powi(2)
             };
-        let path = {
-            let mut builder = Path::builder();
-            builder.add_rounded_rectangle(
-                &Box2D::new(
-                    lyon::math::point(-0.5 * size, -0.5 * size),
-                    lyon::math::point(0.5 * size, 0.5 * size),
-                ),
-                &BorderRadii::new(self.radius),
-                Winding::Positive,
-            );
-            builder.build()
-        };
-        target.fill_path(
-            &path.transformed(&MyTransfrom(*tr)),
+        target.fill_rounded_rect(
+            &Box2D::new(
+                lyon::math::point(-0.5 * size, -0.5 * size),
+                lyon::math::point(0.5 * size, 0.5 * size),
+            ),
+            &BorderRadii::new(self.radius),
+            tr,
             self.color.color().into_shading(),
             1.0,
         );
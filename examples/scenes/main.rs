@@ -4,7 +4,7 @@ mod utils;
 
 use comui::{
     component::Component,
-    components::label::Label,
+    components::{label::Label, stats::StatsOverlay},
     layout::{Layout, LayoutBuilder},
     scene::SceneManager,
     utils::Transform,
@@ -16,7 +16,7 @@ use macroquad::{
     prelude::info,
     window::{clear_background, next_frame},
 };
-use nalgebra::Matrix3;
+use nalgebra::{Matrix3, Vector2};
 
 use crate::{scenes::test_scene::TestScene, utils::Handler};
 
@@ -60,6 +60,8 @@ fn config() -> macroquad::prelude::Conf {
 async fn main() {
     let mut handler = Handler::default();
     let mut main_view = Main::default();
+    let mut window = Window::default();
+    let mut stats = StatsOverlay::new();
     loop {
         clear_background(WHITE);
         handler.update();
@@ -69,7 +71,14 @@ async fn main() {
                 info!("Error handling touch: {:?}", e);
             }
         }
-        main_view.render(&Matrix3::identity(), &mut Window::default());
+        main_view.render(&Matrix3::identity(), &mut window);
+        stats.render(
+            &Transform::new_translation(&Vector2::new(10., 10.)),
+            &mut window,
+        );
+        if let Some(metrics) = window.update() {
+            main_view.resize(&metrics);
+        }
         next_frame().await
     }
 }
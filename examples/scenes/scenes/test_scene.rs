@@ -9,7 +9,6 @@ use crate::components::button::MyFancyBtn;
 pub struct TestScene {
     pub back_btn: MyFancyBtn,
     pub create_new_scene_btn: MyFancyBtn,
-    
 
     next_scene: Option<NextScene>,
 }
@@ -1,5 +1,4 @@
-use comui::{input::subscriber_id, utils::Transform};
-use lyon::geom::traits::Transformation;
+use comui::input::subscriber_id;
 use macroquad::{
     input::{
         MouseButton, Touch, TouchPhase, is_mouse_button_down, mouse_position,
@@ -10,22 +9,6 @@ use macroquad::{
     window::screen_dpi_scale,
 };
 
-pub struct MyTransfrom(pub Transform);
-
-impl Transformation<f32> for MyTransfrom {
-    fn transform_point(&self, p: lyon::geom::Point<f32>) -> lyon::geom::Point<f32> {
-        let p = nalgebra::Point2::new(p.x, p.y);
-        let p = self.0.transform_point(&p);
-        lyon::geom::Point::new(p.x, p.y)
-    }
-
-    fn transform_vector(&self, v: lyon::geom::Vector<f32>) -> lyon::geom::Vector<f32> {
-        let v = nalgebra::Vector2::new(v.x, v.y);
-        let v = self.0.transform_vector(&v);
-        lyon::geom::Vector::new(v.x, v.y)
-    }
-}
-
 fn button_to_id(button: MouseButton) -> u64 {
     u64::MAX
         - match button {
@@ -55,6 +38,7 @@ impl EventHandler for Handler {
         }
     }
 
+    // Event positions are physical, unlike `mouse_position`
     fn mouse_button_down_event(&mut self, button: MouseButton, x: f32, y: f32) {
         self.touches.push(Touch {
             id: button_to_id(button),
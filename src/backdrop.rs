@@ -0,0 +1,32 @@
+/// Settings of [`Window::fill_backdrop_blur`](crate::window::Window::fill_backdrop_blur).
+#[derive(Clone, Debug)]
+pub struct BackdropBlur {
+    /// Blur radius in logical pixels, the standard deviation being half of it.
+    pub radius: f32,
+    /// Blurring at a fraction of the resolution is a lot cheaper and,
+    /// for large radii, hardly visible.
+    pub downsample: u32,
+}
+
+impl Default for BackdropBlur {
+    fn default() -> Self {
+        Self {
+            radius: 16.0,
+            downsample: 2,
+        }
+    }
+}
+
+impl BackdropBlur {
+    pub fn new(radius: f32) -> Self {
+        Self {
+            radius,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_downsample(mut self, downsample: u32) -> Self {
+        self.downsample = downsample;
+        self
+    }
+}
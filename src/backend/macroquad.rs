@@ -0,0 +1,845 @@
+use std::collections::HashMap;
+
+use macroquad::{
+    camera::{Camera2D, pop_camera_state, push_camera_state, set_camera},
+    color::{Color, WHITE},
+    material::{Material, MaterialParams, gl_use_default_material, gl_use_material, load_material},
+    math::{Rect, Vec2, vec2},
+    miniquad::{
+        BlendFactor, BlendState, BlendValue, CompareFunc, Equation, PassAction, PipelineParams,
+        ShaderSource, StencilFaceState, StencilOp, StencilState, UniformDesc, UniformType,
+        native::gl,
+    },
+    prelude::DrawMode,
+    texture::{FilterMode, Image, RenderTarget, Texture2D, render_target},
+    time::get_time,
+    ui::Vertex,
+    window::{clear_background, get_internal_gl, screen_dpi_scale, screen_height, screen_width},
+};
+use tracing::warn;
+
+use super::RenderBackend;
+use crate::{
+    batch::{BlendMode, DrawState},
+    color,
+    material::{MaterialState, RESOLUTION, TIME},
+    shading::{Gradient, GradientKind, Wrap},
+};
+
+/// Same as macroquad's default shader, used for the pipelines comui creates itself.
+pub(crate) mod shader {
+    use crate::shading::Wrap;
+
+    pub const VERTEX: &str = r#"#version 100
+    attribute vec3 position;
+    attribute vec2 texcoord;
+    attribute vec4 color0;
+
+    varying lowp vec2 uv;
+    varying lowp vec4 color;
+
+    uniform mat4 Model;
+    uniform mat4 Projection;
+
+    void main() {
+        gl_Position = Projection * Model * vec4(position, 1);
+        color = color0 / 255.0;
+        uv = texcoord;
+    }"#;
+
+    /// Outputs premultiplied colors, all blend states expect them.
+    pub const FRAGMENT: &str = r#"#version 100
+    varying lowp vec4 color;
+    varying lowp vec2 uv;
+
+    uniform sampler2D Texture;
+
+    void main() {
+        lowp vec4 result = color * texture2D(Texture, uv);
+        gl_FragColor = vec4(result.rgb * result.a, result.a);
+    }"#;
+
+    pub const BLUR_TAPS: i32 = 8;
+
+    /// One direction of a separable gaussian blur, `BLUR_TAPS` samples to each side.
+    ///
+    /// `Step` is the distance between samples in texture coordinates,
+    /// `Sigma` the standard deviation in steps.
+    pub const BLUR: &str = r#"#version 100
+    precision mediump float;
+
+    varying lowp vec4 color;
+    varying lowp vec2 uv;
+
+    uniform sampler2D Texture;
+    uniform vec2 Step;
+    uniform float Sigma;
+
+    void main() {
+        vec4 sum = vec4(0.0);
+        float total = 0.0;
+        for (int i = -8; i <= 8; i++) {
+            float weight = exp(-float(i * i) / (2.0 * Sigma * Sigma));
+            sum += texture2D(Texture, uv + Step * float(i)) * weight;
+            total += weight;
+        }
+        gl_FragColor = sum / total;
+    }"#;
+
+    /// Like `VERTEX`, with precise texture coordinates for gradients and repeating textures.
+    pub const PRECISE_VERTEX: &str = r#"#version 100
+    attribute vec3 position;
+    attribute vec2 texcoord;
+    attribute vec4 color0;
+
+    varying highp vec2 uv;
+    varying lowp vec4 color;
+
+    uniform mat4 Model;
+    uniform mat4 Projection;
+
+    void main() {
+        gl_Position = Projection * Model * vec4(position, 1);
+        color = color0 / 255.0;
+        uv = texcoord;
+    }"#;
+
+    /// Colors a gradient from the lookup table in `Texture`, `offset` being the body
+    /// of a function computing the offset, see `Gradient::offset`.
+    ///
+    /// `Spread` is 0 to pad, 1 to repeat and 2 to reflect.
+    pub fn gradient_fragment(offset: &str) -> String {
+        format!(
+            r#"#version 100
+    precision highp float;
+
+    varying highp vec2 uv;
+    varying lowp vec4 color;
+
+    uniform sampler2D Texture;
+    uniform vec2 Focal;
+    uniform float Spread;
+
+    float offset() {{
+        {offset}
+    }}
+
+    void main() {{
+        float t = offset();
+        if (Spread < 0.5) {{
+            t = clamp(t, 0.0, 1.0);
+        }} else if (Spread < 1.5) {{
+            t = fract(t);
+        }} else {{
+            t = 1.0 - abs(mod(t, 2.0) - 1.0);
+        }}
+        lowp vec4 result = color * texture2D(Texture, vec2((t * {last}.0 + 0.5) / {size}.0, 0.5));
+        gl_FragColor = vec4(result.rgb * result.a, result.a);
+    }}"#,
+            last = GRADIENT_LUT_SIZE - 1,
+            size = GRADIENT_LUT_SIZE,
+        )
+    }
+
+    /// Like `FRAGMENT` or `FRAGMENT_PREMULTIPLIED`, for textures that don't clamp.
+    pub fn wrapped_fragment(wrap: Wrap, premultiplied: bool) -> String {
+        let sample = match wrap {
+            Wrap::Clamp => "texture2D(Texture, uv)",
+            Wrap::Repeat => "texture2D(Texture, fract(uv))",
+            Wrap::Transparent => {
+                "(any(lessThan(uv, vec2(0.0))) || any(greaterThan(uv, vec2(1.0))) ? vec4(0.0) : texture2D(Texture, uv))"
+            }
+        };
+        let output = if premultiplied {
+            "result"
+        } else {
+            "vec4(result.rgb * result.a, result.a)"
+        };
+        format!(
+            r#"#version 100
+    precision highp float;
+
+    varying highp vec2 uv;
+    varying lowp vec4 color;
+
+    uniform sampler2D Texture;
+
+    void main() {{
+        lowp vec4 result = color * {sample};
+        gl_FragColor = {output};
+    }}"#
+        )
+    }
+
+    pub const GRADIENT_LUT_SIZE: u16 = 256;
+
+    pub const LINEAR_OFFSET: &str = "return uv.x;";
+
+    pub const RADIAL_OFFSET: &str = r#"vec2 d = uv - Focal;
+        vec2 e = -Focal;
+        float de = dot(d, e);
+        float dd = dot(d, d);
+        float a = dot(e, e) - 1.0;
+        float t;
+        if (abs(a) < 1e-5) {
+            t = dd / (2.0 * de);
+        } else {
+            float discriminant = de * de - a * dd;
+            if (discriminant < 0.0) {
+                discard;
+            }
+            float root = sqrt(discriminant);
+            t = max((de - root) / a, (de + root) / a);
+        }
+        if (t < 0.0) {
+            discard;
+        }
+        return t;"#;
+
+    pub const CONIC_OFFSET: &str = "return fract(atan(uv.y, uv.x) / 6.28318530718 + 1.0);";
+
+    /// For colors that are already premultiplied.
+    pub const FRAGMENT_PREMULTIPLIED: &str = r#"#version 100
+    varying lowp vec4 color;
+    varying lowp vec2 uv;
+
+    uniform sampler2D Texture;
+
+    void main() {
+        gl_FragColor = color * texture2D(Texture, uv);
+    }"#;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum StencilMode {
+    /// Draws where the stencil value equals the depth
+    Test(u8),
+    /// Increments the stencil where it equals the depth, without drawing any color
+    Increment(u8),
+    /// Decrements the stencil where it equals the depth, without drawing any color
+    Decrement(u8),
+}
+
+/// Everything that requires a dedicated pipeline.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+struct PipelineKey {
+    stencil: Option<StencilMode>,
+    /// Never [`BlendMode::Premultiplied`], see [`DrawState::resolved_blend`]
+    blend: BlendMode,
+    premultiplied: bool,
+    gradient: Option<GradientShader>,
+    wrap: Wrap,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum GradientShader {
+    Linear,
+    Radial,
+    Conic,
+}
+
+impl GradientShader {
+    fn of(kind: &GradientKind) -> Self {
+        match kind {
+            GradientKind::Linear => Self::Linear,
+            GradientKind::Radial { .. } => Self::Radial,
+            GradientKind::Conic => Self::Conic,
+        }
+    }
+
+    fn offset(&self) -> &'static str {
+        match self {
+            Self::Linear => shader::LINEAR_OFFSET,
+            Self::Radial => shader::RADIAL_OFFSET,
+            Self::Conic => shader::CONIC_OFFSET,
+        }
+    }
+}
+
+impl PipelineKey {
+    fn params(&self) -> PipelineParams {
+        let blend = |source, destination| Some(BlendState::new(Equation::Add, source, destination));
+        let one_minus_source_alpha = BlendFactor::OneMinusValue(BlendValue::SourceAlpha);
+        let (color_blend, alpha_blend) = match self.blend {
+            BlendMode::Normal | BlendMode::Premultiplied => (
+                blend(BlendFactor::One, one_minus_source_alpha),
+                blend(BlendFactor::One, one_minus_source_alpha),
+            ),
+            BlendMode::Additive => (
+                blend(BlendFactor::One, BlendFactor::One),
+                blend(BlendFactor::One, BlendFactor::One),
+            ),
+            BlendMode::Multiply => (
+                blend(
+                    BlendFactor::Value(BlendValue::DestinationColor),
+                    one_minus_source_alpha,
+                ),
+                blend(BlendFactor::One, one_minus_source_alpha),
+            ),
+            BlendMode::Screen => (
+                blend(
+                    BlendFactor::One,
+                    BlendFactor::OneMinusValue(BlendValue::SourceColor),
+                ),
+                blend(BlendFactor::One, one_minus_source_alpha),
+            ),
+        };
+        let mut params = PipelineParams {
+            color_blend,
+            alpha_blend,
+            ..Default::default()
+        };
+        if let Some(stencil) = self.stencil {
+            let (depth, pass_op, color_write) = match stencil {
+                StencilMode::Test(depth) => (depth, StencilOp::Keep, true),
+                StencilMode::Increment(depth) => (depth, StencilOp::IncrementClamp, false),
+                StencilMode::Decrement(depth) => (depth, StencilOp::DecrementClamp, false),
+            };
+            let face = StencilFaceState {
+                fail_op: StencilOp::Keep,
+                depth_fail_op: StencilOp::Keep,
+                pass_op,
+                test_func: CompareFunc::Equal,
+                test_ref: depth as i32,
+                test_mask: u32::MAX,
+                write_mask: u32::MAX,
+            };
+            params.stencil_test = Some(StencilState {
+                front: face,
+                back: face,
+            });
+            params.color_write = (color_write, color_write, color_write, color_write);
+        }
+        params
+    }
+}
+
+/// Draws with macroquad, requires a live GL context.
+///
+/// Stencil clipping needs a stencil buffer, which render targets created by macroquad
+/// don't have: there, including in layers, clips only work as their bounding box.
+pub struct MacroquadBackend {
+    draw_call_vertex_capacity: usize,
+    draw_call_index_capacity: usize,
+    stencil_depth: u8,
+    /// `None` if the pipeline failed to build
+    pipelines: HashMap<PipelineKey, Option<Material>>,
+    scissor: Option<Rect>,
+    /// Layers being drawn into, innermost last
+    layers: Vec<RenderTarget>,
+    /// Layers that are free to be reused
+    layer_pool: Vec<RenderTarget>,
+    /// `None` if the pipeline failed to build
+    blur_material: Option<Option<Material>>,
+    blur_pool: Vec<RenderTarget>,
+    /// Downsampling factor `blur_pool` was created with
+    blur_downsample: u32,
+    snapshot_pool: Vec<RenderTarget>,
+    /// Copy of the screen as it is stored, bottom to top
+    screen_grab: Option<Texture2D>,
+    /// Lookup tables of gradients, by the hash of their stops
+    gradient_luts: HashMap<u64, Texture2D>,
+}
+
+impl Default for MacroquadBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MacroquadBackend {
+    const MAX_GRADIENT_LUTS: usize = 64;
+
+    pub fn new() -> Self {
+        // Defaults of `macroquad::conf::Conf`
+        Self {
+            draw_call_vertex_capacity: 10000,
+            draw_call_index_capacity: 5000,
+            stencil_depth: 0,
+            pipelines: HashMap::new(),
+            scissor: None,
+            layers: Vec::new(),
+            layer_pool: Vec::new(),
+            blur_material: None,
+            blur_pool: Vec::new(),
+            blur_downsample: 1,
+            snapshot_pool: Vec::new(),
+            screen_grab: None,
+            gradient_luts: HashMap::new(),
+        }
+    }
+
+    /// Must match `draw_call_vertex_capacity` and `draw_call_index_capacity`
+    /// of the `macroquad::conf::Conf` the app was started with.
+    pub fn with_draw_call_capacity(mut self, vertices: usize, indices: usize) -> Self {
+        self.draw_call_vertex_capacity = vertices;
+        self.draw_call_index_capacity = indices;
+        self
+    }
+
+    /// Returns the material of the pipeline, `None` if it failed to build.
+    fn use_pipeline(&mut self, key: PipelineKey) -> Option<Material> {
+        let material = self.pipelines.entry(key).or_insert_with(|| {
+            let generated_fragment;
+            let (vertex, fragment, uniforms) = match key.gradient {
+                Some(gradient) => {
+                    generated_fragment = shader::gradient_fragment(gradient.offset());
+                    (
+                        shader::PRECISE_VERTEX,
+                        generated_fragment.as_str(),
+                        vec![
+                            UniformDesc::new("Focal", UniformType::Float2),
+                            UniformDesc::new("Spread", UniformType::Float1),
+                        ],
+                    )
+                }
+                None if key.wrap != Wrap::Clamp => {
+                    generated_fragment = shader::wrapped_fragment(key.wrap, key.premultiplied);
+                    (
+                        shader::PRECISE_VERTEX,
+                        generated_fragment.as_str(),
+                        Vec::new(),
+                    )
+                }
+                None if key.premultiplied => {
+                    (shader::VERTEX, shader::FRAGMENT_PREMULTIPLIED, Vec::new())
+                }
+                None => (shader::VERTEX, shader::FRAGMENT, Vec::new()),
+            };
+            load_material(
+                ShaderSource::Glsl { vertex, fragment },
+                MaterialParams {
+                    pipeline_params: key.params(),
+                    uniforms,
+                    ..Default::default()
+                },
+            )
+            .inspect_err(|e| warn!("Failed to build pipeline for {:?}: {}", key, e))
+            .ok()
+        });
+        match material {
+            Some(material) => gl_use_material(material),
+            None => gl_use_default_material(),
+        }
+        material.clone()
+    }
+
+    /// Lookup table of the colors of `gradient`, cached by its stops.
+    fn gradient_lut(&mut self, gradient: &Gradient) -> Texture2D {
+        let hash = gradient.stops_hash();
+        if let Some(texture) = self.gradient_luts.get(&hash) {
+            return texture.clone();
+        }
+        if self.gradient_luts.len() >= Self::MAX_GRADIENT_LUTS {
+            self.gradient_luts.clear();
+        }
+        let size = shader::GRADIENT_LUT_SIZE;
+        let bytes = (0..size)
+            .flat_map(|i| color::to_rgba8(gradient.color(i as f32 / (size - 1) as f32)))
+            .collect::<Vec<_>>();
+        let texture = Texture2D::from_rgba8(size, 1, &bytes);
+        texture.set_filter(FilterMode::Linear);
+        self.gradient_luts.insert(hash, texture.clone());
+        texture
+    }
+
+    fn geometry(&mut self, key: PipelineKey, vertices: &[Vertex], indices: &[u16]) {
+        self.use_pipeline(key);
+        let gl = unsafe { get_internal_gl() }.quad_gl;
+        gl.texture(None);
+        gl.draw_mode(DrawMode::Triangles);
+        gl.geometry(vertices, indices);
+        gl_use_default_material();
+    }
+
+    fn clear_stencil(&mut self) {
+        let mut gl = unsafe { get_internal_gl() };
+        gl.flush();
+        let pass = gl.quad_gl.get_active_render_pass();
+        gl.quad_context.begin_pass(
+            pass,
+            PassAction::Clear {
+                color: None,
+                depth: None,
+                stencil: Some(0),
+            },
+        );
+        gl.quad_context.end_render_pass();
+    }
+
+    /// Physical size of the screen, which is also the size of layers.
+    fn physical_size() -> (u32, u32) {
+        let dpi_scale = screen_dpi_scale();
+        (
+            (screen_width() * dpi_scale).round() as u32,
+            (screen_height() * dpi_scale).round() as u32,
+        )
+    }
+
+    /// Draws to `target` with the same coordinates as the screen.
+    ///
+    /// The first switch away from the screen must be preceded by `push_camera_state`.
+    fn render_to(target: &RenderTarget) {
+        let (width, height) = (screen_width(), screen_height());
+        // Unlike macroquad's usual render target cameras, rows are stored top to bottom
+        // so that render targets can be sampled just like the screen
+        set_camera(&Camera2D {
+            zoom: vec2(2.0 / width, 2.0 / height),
+            target: vec2(width / 2.0, height / 2.0),
+            render_target: Some(target.clone()),
+            ..Default::default()
+        });
+    }
+
+    /// Goes back to drawing to the innermost layer, or to whatever was there before the first one.
+    fn restore_target(&mut self) {
+        match self.layers.last() {
+            Some(layer) => Self::render_to(layer),
+            None => pop_camera_state(),
+        }
+        self.set_scissor(self.scissor);
+    }
+
+    /// Takes a screen-sized render target from `pool`, divided by `downsample`.
+    fn pooled_target(pool: &mut Vec<RenderTarget>, downsample: u32) -> RenderTarget {
+        let (width, height) = Self::physical_size();
+        let (width, height) = (width.div_ceil(downsample), height.div_ceil(downsample));
+        // Targets left over from before a resize are of no use anymore
+        pool.retain(|target| target.texture.size() == vec2(width as f32, height as f32));
+        pool.pop().unwrap_or_else(|| {
+            let target = render_target(width, height);
+            target.texture.set_filter(FilterMode::Linear);
+            target
+        })
+    }
+
+    /// Draws the `region` of `source` to `target` with one direction of the blur,
+    /// or copies it when `step` is zero.
+    ///
+    /// `flip_y` is for sources stored bottom to top, like the screen.
+    fn blur_pass(
+        &mut self,
+        source: &Texture2D,
+        target: &RenderTarget,
+        region: Rect,
+        step: Vec2,
+        sigma: f32,
+        flip_y: bool,
+    ) {
+        let Some(material) = self
+            .blur_material
+            .get_or_insert_with(|| {
+                load_material(
+                    ShaderSource::Glsl {
+                        vertex: shader::VERTEX,
+                        fragment: shader::BLUR,
+                    },
+                    MaterialParams {
+                        pipeline_params: PipelineParams::default(),
+                        uniforms: vec![
+                            UniformDesc::new("Step", UniformType::Float2),
+                            UniformDesc::new("Sigma", UniformType::Float1),
+                        ],
+                        ..Default::default()
+                    },
+                )
+                .inspect_err(|e| warn!("Failed to build the blur pipeline: {}", e))
+                .ok()
+            })
+            .as_ref()
+        else {
+            return;
+        };
+        Self::render_to(target);
+        clear_background(Color::new(0.0, 0.0, 0.0, 0.0));
+        material.set_uniform("Step", step);
+        material.set_uniform("Sigma", sigma);
+        gl_use_material(material);
+        let (width, height) = (screen_width(), screen_height());
+        let vertices = [
+            (region.x, region.y),
+            (region.right(), region.y),
+            (region.right(), region.bottom()),
+            (region.x, region.bottom()),
+        ]
+        .map(|(x, y)| {
+            let v = if flip_y { 1.0 - y / height } else { y / height };
+            Vertex::new(x, y, 0., x / width, v, WHITE)
+        });
+        let gl = unsafe { get_internal_gl() }.quad_gl;
+        gl.scissor(None);
+        gl.texture(Some(source));
+        gl.draw_mode(DrawMode::Triangles);
+        gl.geometry(&vertices, &[0, 1, 2, 0, 2, 3]);
+        gl_use_default_material();
+    }
+}
+
+impl RenderBackend for MacroquadBackend {
+    fn screen_size(&self) -> (f32, f32) {
+        (screen_width(), screen_height())
+    }
+
+    fn dpi_scale(&self) -> f32 {
+        screen_dpi_scale()
+    }
+
+    fn max_texture_size(&self) -> u32 {
+        let mut length: i32 = 0;
+        unsafe {
+            gl::glGetIntegerv(gl::GL_MAX_TEXTURE_SIZE, &mut length);
+        }
+        length.max(0) as u32
+    }
+
+    fn new_texture(&mut self, width: u32, height: u32) -> Texture2D {
+        render_target(width, height).texture
+    }
+
+    fn texture_size(&self, texture: &Texture2D) -> Option<(u32, u32)> {
+        Some((texture.width() as u32, texture.height() as u32))
+    }
+
+    fn update_texture(&mut self, texture: &Texture2D, x: i32, y: i32, image: &Image) {
+        texture.update_part(image, x, y, image.width as i32, image.height as i32);
+    }
+
+    fn set_camera(&mut self, camera: &Camera2D) {
+        set_camera(camera);
+    }
+
+    fn set_scissor(&mut self, rect: Option<Rect>) {
+        self.scissor = rect;
+        let dpi_scale = screen_dpi_scale();
+        let flipped = !self.layers.is_empty();
+        let (_, physical_height) = Self::physical_size();
+        let gl = unsafe { get_internal_gl() }.quad_gl;
+        gl.scissor(rect.map(|rect| {
+            let x = (rect.x * dpi_scale).round() as i32;
+            let mut y = (rect.y * dpi_scale).round() as i32;
+            let width = (rect.right() * dpi_scale).round() as i32 - x;
+            let height = (rect.bottom() * dpi_scale).round() as i32 - y;
+            if flipped {
+                // macroquad flips the scissor vertically, layers are already flipped
+                y = physical_height as i32 - y - height;
+            }
+            (x, y, width, height)
+        }));
+    }
+
+    fn push_stencil(&mut self, vertices: &[Vertex], indices: &[u16]) {
+        if self.stencil_depth == u8::MAX {
+            warn!("Too many nested stencil clips, ignoring");
+            return;
+        }
+        if self.stencil_depth == 0 {
+            self.clear_stencil();
+        }
+        let key = PipelineKey {
+            stencil: Some(StencilMode::Increment(self.stencil_depth)),
+            ..Default::default()
+        };
+        self.geometry(key, vertices, indices);
+        self.stencil_depth += 1;
+    }
+
+    fn pop_stencil(&mut self, vertices: &[Vertex], indices: &[u16]) {
+        if self.stencil_depth == 0 {
+            return;
+        }
+        let key = PipelineKey {
+            stencil: Some(StencilMode::Decrement(self.stencil_depth)),
+            ..Default::default()
+        };
+        self.geometry(key, vertices, indices);
+        self.stencil_depth -= 1;
+    }
+
+    fn push_layer(&mut self) {
+        let target = Self::pooled_target(&mut self.layer_pool, 1);
+        if self.layers.is_empty() {
+            push_camera_state();
+        }
+        self.layers.push(target.clone());
+        Self::render_to(&target);
+        self.set_scissor(self.scissor);
+        clear_background(Color::new(0.0, 0.0, 0.0, 0.0));
+    }
+
+    fn pop_layer(&mut self) -> Texture2D {
+        let Some(target) = self.layers.pop() else {
+            warn!("pop_layer without a matching push_layer");
+            return Texture2D::empty();
+        };
+        self.restore_target();
+        // Switching the render pass flushed every draw into the layer,
+        // drawing it is queued before anything could reuse it
+        let texture = target.texture.clone();
+        self.layer_pool.push(target);
+        texture
+    }
+
+    fn snapshot(&mut self, region: Rect) -> Texture2D {
+        let target = Self::pooled_target(&mut self.snapshot_pool, 1);
+        let (source, flip_y) = match self.layers.last() {
+            Some(layer) => (layer.texture.clone(), false),
+            None => {
+                let (width, height) = Self::physical_size();
+                let size = vec2(width as f32, height as f32);
+                let grab = match self.screen_grab.take() {
+                    Some(grab) if grab.size() == size => grab,
+                    _ => render_target(width, height).texture,
+                };
+                // Everything queued must be on the screen before copying it
+                unsafe { get_internal_gl() }.flush();
+                grab.grab_screen();
+                self.screen_grab = Some(grab.clone());
+                (grab, true)
+            }
+        };
+        if self.layers.is_empty() {
+            push_camera_state();
+        }
+        self.blur_pass(&source, &target, region, Vec2::ZERO, 1.0, flip_y);
+        self.restore_target();
+        let result = target.texture.clone();
+        self.snapshot_pool.push(target);
+        result
+    }
+
+    fn read_texture(&mut self, texture: &Texture2D) -> Image {
+        // Draws into the texture may still be queued
+        unsafe { get_internal_gl() }.flush();
+        texture.get_texture_data()
+    }
+
+    fn blur(
+        &mut self,
+        texture: &Texture2D,
+        region: Rect,
+        radius: f32,
+        downsample: u32,
+    ) -> Texture2D {
+        let downsample = downsample.max(1);
+        if self.blur_downsample != downsample {
+            self.blur_pool.clear();
+            self.blur_downsample = downsample;
+        }
+        let first = Self::pooled_target(&mut self.blur_pool, downsample);
+        let second = Self::pooled_target(&mut self.blur_pool, downsample);
+        let (width, height) = Self::physical_size();
+        let (width, height) = (
+            width.div_ceil(downsample) as f32,
+            height.div_ceil(downsample) as f32,
+        );
+        // In texels of the downsampled targets
+        let sigma = (radius * screen_dpi_scale() / downsample as f32 / 2.0).max(1e-3);
+        let step = (sigma * 3.0 / shader::BLUR_TAPS as f32).max(1.0);
+        // The first pass must also cover what the second one samples
+        let margin = sigma * 3.0 * downsample as f32 / screen_dpi_scale();
+        let outer = Rect::new(
+            region.x,
+            region.y - margin,
+            region.w,
+            region.h + margin * 2.0,
+        );
+        if self.layers.is_empty() {
+            push_camera_state();
+        }
+        self.blur_pass(
+            texture,
+            &first,
+            outer,
+            vec2(step / width, 0.0),
+            sigma / step,
+            false,
+        );
+        self.blur_pass(
+            &first.texture,
+            &second,
+            region,
+            vec2(0.0, step / height),
+            sigma / step,
+            false,
+        );
+        self.restore_target();
+        let result = second.texture.clone();
+        // Switching the render pass flushed the passes, so the targets can be reused
+        // as soon as the result has been drawn
+        self.blur_pool.push(first);
+        self.blur_pool.push(second);
+        result
+    }
+
+    fn max_submission_size(&self) -> (usize, usize) {
+        // macroquad truncates geometry reaching the capacity
+        (
+            (self.draw_call_vertex_capacity - 1).min(u16::MAX as usize + 1),
+            self.draw_call_index_capacity - 1,
+        )
+    }
+
+    fn draw_triangles(&mut self, vertices: &[Vertex], indices: &[u16], state: &DrawState) {
+        // The material's pipeline has its own blending and no stencil test
+        let material = state.material.as_ref().filter(|_| {
+            let supported = self.stencil_depth == 0
+                && matches!(state.blend, BlendMode::Normal | BlendMode::Premultiplied);
+            if !supported {
+                warn!(
+                    "Materials can't be drawn with {:?} blending or within stencil clips, drawing the vertex colors instead",
+                    state.blend
+                );
+            }
+            supported
+        });
+        if let Some(MaterialState { material, uniforms }) = material {
+            let dpi_scale = screen_dpi_scale();
+            material.set_uniform(TIME, get_time() as f32);
+            material.set_uniform(
+                RESOLUTION,
+                vec2(screen_width() * dpi_scale, screen_height() * dpi_scale),
+            );
+            for (name, uniform) in uniforms {
+                uniform.apply(material, name);
+            }
+            gl_use_material(material);
+            let gl = unsafe { get_internal_gl() }.quad_gl;
+            gl.texture(state.texture.as_ref());
+            gl.draw_mode(DrawMode::Triangles);
+            gl.geometry(vertices, indices);
+            gl_use_default_material();
+            return;
+        }
+        let (blend, premultiplied) = state.resolved_blend();
+        let material = self.use_pipeline(PipelineKey {
+            stencil: (self.stencil_depth > 0).then_some(StencilMode::Test(self.stencil_depth)),
+            blend,
+            premultiplied,
+            gradient: state
+                .gradient
+                .as_ref()
+                .map(|gradient| GradientShader::of(&gradient.kind)),
+            wrap: state.wrap,
+        });
+        let texture = match &state.gradient {
+            Some(gradient) => {
+                if let Some(material) = &material {
+                    let focal = match gradient.kind {
+                        GradientKind::Radial { focal } => focal,
+                        _ => Vec2::ZERO,
+                    };
+                    material.set_uniform("Focal", focal);
+                    material.set_uniform("Spread", gradient.spread as u8 as f32);
+                }
+                Some(self.gradient_lut(gradient))
+            }
+            None => state.texture.clone(),
+        };
+        let gl = unsafe { get_internal_gl() }.quad_gl;
+        gl.texture(texture.as_ref());
+        gl.draw_mode(DrawMode::Triangles);
+        gl.geometry(vertices, indices);
+        // Raw macroquad draws after comui's must not inherit its pipelines
+        gl_use_default_material();
+    }
+}
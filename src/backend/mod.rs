@@ -0,0 +1,95 @@
+//! Render backends.
+//!
+//! [`Window`](crate::window::Window) never talks to the GPU directly, everything it
+//! draws ends up in a [`RenderBackend`]. [`MacroquadBackend`] is the default one,
+//! [`RecordingBackend`] keeps everything in memory and [`SoftwareBackend`] rasterizes
+//! on the CPU, neither of them needs a GL context.
+
+mod macroquad;
+mod recording;
+mod software;
+
+pub use self::macroquad::MacroquadBackend;
+pub(crate) use self::macroquad::shader::VERTEX as DEFAULT_VERTEX_SHADER;
+pub use self::recording::{Command, RecordingBackend};
+pub use self::software::{ImageDiff, SoftwareBackend, compare_images};
+
+use std::any::Any;
+
+use ::macroquad::{
+    camera::Camera2D,
+    math::Rect,
+    texture::{Image, Texture2D},
+    ui::Vertex,
+};
+
+use crate::batch::DrawState;
+
+pub trait RenderBackend: Any {
+    /// Size of the drawing area in logical pixels.
+    fn screen_size(&self) -> (f32, f32);
+    fn dpi_scale(&self) -> f32;
+
+    /// The largest texture (in pixels, per side) this backend can create.
+    fn max_texture_size(&self) -> u32;
+    /// Creates an empty RGBA texture.
+    fn new_texture(&mut self, width: u32, height: u32) -> Texture2D;
+    /// Size of `texture` in pixels, `None` for textures this backend doesn't know.
+    fn texture_size(&self, texture: &Texture2D) -> Option<(u32, u32)>;
+    /// Uploads `image` to `texture`, with its top-left corner at (`x`, `y`).
+    fn update_texture(&mut self, texture: &Texture2D, x: i32, y: i32, image: &Image);
+
+    fn set_camera(&mut self, camera: &Camera2D);
+
+    /// Restricts drawing to `rect`, in logical pixels. `None` lifts the restriction.
+    fn set_scissor(&mut self, rect: Option<Rect>);
+    /// Restricts drawing to the inside of the triangles, within all previously pushed stencils.
+    fn push_stencil(&mut self, vertices: &[Vertex], indices: &[u16]);
+    /// Undoes the last [`Self::push_stencil`], called with the same geometry.
+    fn pop_stencil(&mut self, vertices: &[Vertex], indices: &[u16]);
+
+    /// Redirects drawing into a new, transparent offscreen layer the size of the screen.
+    ///
+    /// Layers nest, everything else (camera, scissor, stencil) carries over.
+    fn push_layer(&mut self);
+    /// Ends the last [`Self::push_layer`] and returns the content of the layer as a
+    /// premultiplied texture covering the screen, rows top to bottom.
+    ///
+    /// The texture is reused by later layers, so it must be drawn before the next
+    /// [`Self::push_layer`].
+    fn pop_layer(&mut self) -> Texture2D;
+
+    /// Copies what has been drawn to the current target, the screen or the innermost layer,
+    /// into a premultiplied texture covering the screen, rows top to bottom.
+    ///
+    /// Only the `region` (logical pixels) of the texture is valid. It is reused
+    /// by later snapshots, so it must be used before the next [`Self::snapshot`].
+    fn snapshot(&mut self, region: Rect) -> Texture2D;
+
+    /// Reads the pixels of `texture` back, rows top to bottom.
+    ///
+    /// This waits for everything drawn so far to finish, so it is slow.
+    fn read_texture(&mut self, texture: &Texture2D) -> Image;
+
+    /// Blurs the `region` (logical pixels) of a premultiplied texture covering the screen,
+    /// such as a layer, with a gaussian of standard deviation `radius / 2` logical pixels.
+    ///
+    /// The result covers the screen as well but is only valid inside `region`. It is reused
+    /// by later blurs, so it must be drawn before the next [`Self::blur`]. A `downsample`
+    /// factor above 1 trades quality for speed, backends may ignore it.
+    fn blur(
+        &mut self,
+        texture: &Texture2D,
+        region: Rect,
+        radius: f32,
+        downsample: u32,
+    ) -> Texture2D;
+
+    /// Largest number of vertices and indices a single [`Self::draw_triangles`] call may take.
+    fn max_submission_size(&self) -> (usize, usize) {
+        (u16::MAX as usize + 1, usize::MAX)
+    }
+
+    /// `vertices` are in the final coordinate system, `texture: None` means a plain white texture.
+    fn draw_triangles(&mut self, vertices: &[Vertex], indices: &[u16], state: &DrawState);
+}
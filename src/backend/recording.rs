@@ -0,0 +1,253 @@
+use macroquad::{
+    camera::{Camera, Camera2D},
+    color::Color,
+    math::{Mat4, Rect},
+    miniquad::{RawId, TextureId},
+    texture::{Image, Texture2D},
+    ui::Vertex,
+};
+
+use super::RenderBackend;
+use crate::batch::DrawState;
+
+#[derive(Clone, Debug)]
+pub enum Command {
+    NewTexture {
+        texture: Texture2D,
+        width: u32,
+        height: u32,
+    },
+    UpdateTexture {
+        texture: Texture2D,
+        x: i32,
+        y: i32,
+        image: Image,
+    },
+    SetCamera(Mat4),
+    SetScissor(Option<Rect>),
+    PushStencil {
+        vertices: Vec<Vertex>,
+        indices: Vec<u16>,
+    },
+    PopStencil {
+        vertices: Vec<Vertex>,
+        indices: Vec<u16>,
+    },
+    PushLayer,
+    Snapshot {
+        region: Rect,
+        /// Where the copy ends up
+        result: Texture2D,
+    },
+    Blur {
+        texture: Texture2D,
+        region: Rect,
+        radius: f32,
+        downsample: u32,
+        /// Where the result ends up
+        result: Texture2D,
+    },
+    /// Reading back a texture, which yields a transparent image
+    ReadTexture(Texture2D),
+    /// The layer's content ends up in `texture`
+    PopLayer(Texture2D),
+    DrawTriangles {
+        vertices: Vec<Vertex>,
+        indices: Vec<u16>,
+        state: DrawState,
+    },
+}
+
+/// Records every command in memory instead of drawing it.
+///
+/// Textures created by this backend are fake handles, they must not be handed to macroquad.
+pub struct RecordingBackend {
+    pub screen_size: (f32, f32),
+    pub dpi_scale: f32,
+    pub max_texture_size: u32,
+    /// `(vertices, indices)`, see [`RenderBackend::max_submission_size`]
+    pub max_submission_size: (usize, usize),
+    pub commands: Vec<Command>,
+    next_texture_id: u32,
+    /// Sizes of the textures made by [`RenderBackend::new_texture`]
+    texture_sizes: Vec<(Texture2D, u32, u32)>,
+    /// Layers being drawn into, innermost last
+    layers: Vec<Texture2D>,
+    layer_pool: Vec<Texture2D>,
+}
+
+impl RecordingBackend {
+    pub fn new(width: f32, height: f32) -> Self {
+        Self {
+            screen_size: (width, height),
+            dpi_scale: 1.0,
+            max_texture_size: 4096,
+            max_submission_size: (u16::MAX as usize + 1, usize::MAX),
+            commands: Vec::new(),
+            next_texture_id: 1,
+            texture_sizes: Vec::new(),
+            layers: Vec::new(),
+            layer_pool: Vec::new(),
+        }
+    }
+
+    pub fn with_dpi_scale(mut self, dpi_scale: f32) -> Self {
+        self.dpi_scale = dpi_scale;
+        self
+    }
+
+    /// Takes all recorded commands, leaving the log empty.
+    pub fn take_commands(&mut self) -> Vec<Command> {
+        std::mem::take(&mut self.commands)
+    }
+
+    /// Only the [`Command::DrawTriangles`] commands, as `(vertices, indices, state)`.
+    pub fn draw_calls(&self) -> impl Iterator<Item = (&[Vertex], &[u16], &DrawState)> {
+        self.commands.iter().filter_map(|command| match command {
+            Command::DrawTriangles {
+                vertices,
+                indices,
+                state,
+            } => Some((vertices.as_slice(), indices.as_slice(), state)),
+            _ => None,
+        })
+    }
+
+    fn fake_texture(&mut self) -> Texture2D {
+        let texture = Texture2D::from_miniquad_texture(TextureId::from_raw_id(RawId::OpenGl(
+            self.next_texture_id,
+        )));
+        self.next_texture_id += 1;
+        texture
+    }
+}
+
+impl RenderBackend for RecordingBackend {
+    fn screen_size(&self) -> (f32, f32) {
+        self.screen_size
+    }
+
+    fn dpi_scale(&self) -> f32 {
+        self.dpi_scale
+    }
+
+    fn max_texture_size(&self) -> u32 {
+        self.max_texture_size
+    }
+
+    fn max_submission_size(&self) -> (usize, usize) {
+        self.max_submission_size
+    }
+
+    fn new_texture(&mut self, width: u32, height: u32) -> Texture2D {
+        let texture = self.fake_texture();
+        self.texture_sizes.push((texture.clone(), width, height));
+        self.commands.push(Command::NewTexture {
+            texture: texture.clone(),
+            width,
+            height,
+        });
+        texture
+    }
+
+    fn texture_size(&self, texture: &Texture2D) -> Option<(u32, u32)> {
+        self.texture_sizes
+            .iter()
+            .find(|(t, _, _)| t == texture)
+            .map(|&(_, width, height)| (width, height))
+    }
+
+    fn update_texture(&mut self, texture: &Texture2D, x: i32, y: i32, image: &Image) {
+        self.commands.push(Command::UpdateTexture {
+            texture: texture.clone(),
+            x,
+            y,
+            image: image.clone(),
+        });
+    }
+
+    fn set_camera(&mut self, camera: &Camera2D) {
+        self.commands.push(Command::SetCamera(camera.matrix()));
+    }
+
+    fn set_scissor(&mut self, rect: Option<Rect>) {
+        self.commands.push(Command::SetScissor(rect));
+    }
+
+    fn push_stencil(&mut self, vertices: &[Vertex], indices: &[u16]) {
+        self.commands.push(Command::PushStencil {
+            vertices: vertices.to_vec(),
+            indices: indices.to_vec(),
+        });
+    }
+
+    fn pop_stencil(&mut self, vertices: &[Vertex], indices: &[u16]) {
+        self.commands.push(Command::PopStencil {
+            vertices: vertices.to_vec(),
+            indices: indices.to_vec(),
+        });
+    }
+
+    fn push_layer(&mut self) {
+        let texture = match self.layer_pool.pop() {
+            Some(texture) => texture,
+            None => self.fake_texture(),
+        };
+        self.layers.push(texture);
+        self.commands.push(Command::PushLayer);
+    }
+
+    fn pop_layer(&mut self) -> Texture2D {
+        let texture = match self.layers.pop() {
+            Some(texture) => texture,
+            None => self.fake_texture(),
+        };
+        self.layer_pool.push(texture.clone());
+        self.commands.push(Command::PopLayer(texture.clone()));
+        texture
+    }
+
+    fn snapshot(&mut self, region: Rect) -> Texture2D {
+        let result = self.fake_texture();
+        self.commands.push(Command::Snapshot {
+            region,
+            result: result.clone(),
+        });
+        result
+    }
+
+    fn read_texture(&mut self, texture: &Texture2D) -> Image {
+        self.commands.push(Command::ReadTexture(texture.clone()));
+        let (width, height) = self.texture_size(texture).unwrap_or((
+            (self.screen_size.0 * self.dpi_scale).round() as u32,
+            (self.screen_size.1 * self.dpi_scale).round() as u32,
+        ));
+        Image::gen_image_color(width as u16, height as u16, Color::new(0., 0., 0., 0.))
+    }
+
+    fn blur(
+        &mut self,
+        texture: &Texture2D,
+        region: Rect,
+        radius: f32,
+        downsample: u32,
+    ) -> Texture2D {
+        let result = self.fake_texture();
+        self.commands.push(Command::Blur {
+            texture: texture.clone(),
+            region,
+            radius,
+            downsample,
+            result: result.clone(),
+        });
+        result
+    }
+
+    fn draw_triangles(&mut self, vertices: &[Vertex], indices: &[u16], state: &DrawState) {
+        self.commands.push(Command::DrawTriangles {
+            vertices: vertices.to_vec(),
+            indices: indices.to_vec(),
+            state: state.clone(),
+        });
+    }
+}
@@ -0,0 +1,714 @@
+use std::path::Path;
+
+use macroquad::{
+    camera::{Camera, Camera2D},
+    color::Color,
+    math::{Mat4, Rect, Vec2, vec2, vec4},
+    miniquad::{RawId, TextureId},
+    texture::{Image, Texture2D},
+    ui::Vertex,
+};
+
+use super::RenderBackend;
+use crate::{
+    batch::{BlendMode, DrawState},
+    shading::Wrap,
+    utils::{load_png, save_png},
+};
+
+/// Rasterizes everything on the CPU into an RGBA image, no GL context needed.
+///
+/// There is no anti-aliasing: a pixel is covered when its center is inside a triangle.
+/// Textures used for drawing must be created by this backend, either through
+/// [`RenderBackend::new_texture`] or [`SoftwareBackend::add_texture`].
+pub struct SoftwareBackend {
+    /// Logical size
+    size: (f32, f32),
+    dpi_scale: f32,
+    /// Physical size
+    width: usize,
+    height: usize,
+    /// Premultiplied RGBA
+    pixels: Vec<[f32; 4]>,
+    camera: Option<Mat4>,
+    /// Physical pixel bounds, see [`Self::bounds`]
+    scissor: Option<Bounds>,
+    stencil: Vec<u8>,
+    stencil_depth: u8,
+    textures: Vec<(Texture2D, Image)>,
+    next_texture_id: u32,
+    /// Pixels of whatever was drawn into before each active layer, with the layer's texture
+    layers: Vec<(Vec<[f32; 4]>, Texture2D)>,
+    layer_pool: Vec<Texture2D>,
+    /// Where the last blur ended up
+    blur_result: Option<Texture2D>,
+    /// Where the last snapshot ended up
+    snapshot: Option<Texture2D>,
+    pub max_texture_size: u32,
+}
+
+/// Result of comparing two images with [`compare_images`].
+#[derive(Clone, Debug, Default)]
+pub struct ImageDiff {
+    /// Largest per-channel difference
+    pub max_difference: u8,
+    /// Number of pixels with a channel differing by more than the tolerance
+    pub mismatched_pixels: usize,
+    pub size_mismatch: bool,
+}
+
+impl ImageDiff {
+    pub fn is_match(&self) -> bool {
+        !self.size_mismatch && self.mismatched_pixels == 0
+    }
+}
+
+/// Compares two RGBA images channel by channel, allowing each channel to differ by `tolerance`.
+pub fn compare_images(actual: &Image, expected: &Image, tolerance: u8) -> ImageDiff {
+    if actual.width != expected.width || actual.height != expected.height {
+        return ImageDiff {
+            size_mismatch: true,
+            ..Default::default()
+        };
+    }
+    let mut diff = ImageDiff::default();
+    for (a, e) in actual
+        .get_image_data()
+        .iter()
+        .zip(expected.get_image_data())
+    {
+        let max = a
+            .iter()
+            .zip(e)
+            .map(|(a, e)| a.abs_diff(*e))
+            .max()
+            .unwrap_or(0);
+        diff.max_difference = diff.max_difference.max(max);
+        if max > tolerance {
+            diff.mismatched_pixels += 1;
+        }
+    }
+    diff
+}
+
+impl SoftwareBackend {
+    /// `width` and `height` are logical, the image is `dpi_scale` times larger.
+    pub fn new(width: f32, height: f32) -> Self {
+        let mut backend = Self {
+            size: (width, height),
+            dpi_scale: 1.0,
+            width: 0,
+            height: 0,
+            pixels: Vec::new(),
+            camera: None,
+            scissor: None,
+            stencil: Vec::new(),
+            stencil_depth: 0,
+            textures: Vec::new(),
+            next_texture_id: 1,
+            layers: Vec::new(),
+            layer_pool: Vec::new(),
+            blur_result: None,
+            snapshot: None,
+            max_texture_size: 2048,
+        };
+        backend.resize(width, height, 1.0);
+        backend
+    }
+
+    pub fn with_dpi_scale(mut self, dpi_scale: f32) -> Self {
+        self.resize(self.size.0, self.size.1, dpi_scale);
+        self
+    }
+
+    /// Resizes the canvas, clearing it.
+    pub fn resize(&mut self, width: f32, height: f32, dpi_scale: f32) {
+        self.size = (width, height);
+        self.dpi_scale = dpi_scale;
+        self.width = (width * dpi_scale).round() as usize;
+        self.height = (height * dpi_scale).round() as usize;
+        self.pixels = vec![[0.0; 4]; self.width * self.height];
+        self.stencil = vec![0; self.width * self.height];
+    }
+
+    pub fn clear(&mut self, color: Color) {
+        let color = premultiply([color.r, color.g, color.b, color.a]);
+        self.pixels.fill(color);
+    }
+
+    /// Registers an image as a texture usable by this backend.
+    pub fn add_texture(&mut self, image: Image) -> Texture2D {
+        let texture = Texture2D::from_miniquad_texture(TextureId::from_raw_id(RawId::OpenGl(
+            self.next_texture_id,
+        )));
+        self.next_texture_id += 1;
+        self.textures.push((texture.clone(), image));
+        texture
+    }
+
+    /// The current content of the canvas, rows top to bottom.
+    pub fn image(&self) -> Image {
+        Image {
+            bytes: self
+                .pixels
+                .iter()
+                .flat_map(|p| {
+                    let [r, g, b, a] = unpremultiply(*p);
+                    [to_u8(r), to_u8(g), to_u8(b), to_u8(a)]
+                })
+                .collect(),
+            width: self.width as u16,
+            height: self.height as u16,
+        }
+    }
+
+    pub fn save_png(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        save_png(&self.image(), path)
+    }
+
+    /// Compares the canvas with the golden image at `path`.
+    ///
+    /// The golden image is (re)written instead when `COMUI_UPDATE_GOLDEN` is set,
+    /// a missing one is an error otherwise.
+    pub fn check_golden(&self, path: impl AsRef<Path>, tolerance: u8) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        if std::env::var_os("COMUI_UPDATE_GOLDEN").is_some() {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            return self.save_png(path);
+        }
+        anyhow::ensure!(
+            path.exists(),
+            "golden image {} is missing, run with COMUI_UPDATE_GOLDEN=1 to create it",
+            path.display()
+        );
+        let actual = self.image();
+        let diff = compare_images(&actual, &load_png(path)?, tolerance);
+        if !diff.is_match() {
+            let actual_path = path.with_extension("actual.png");
+            save_png(&actual, &actual_path)?;
+            anyhow::bail!(
+                "{} does not match the golden image ({:?}), actual output written to {}",
+                path.display(),
+                diff,
+                actual_path.display()
+            );
+        }
+        Ok(())
+    }
+
+    /// Maps a vertex position to physical pixel coordinates.
+    fn project(&self, vertex: &Vertex) -> Vec2 {
+        match self.camera {
+            None => vec2(vertex.position.x, vertex.position.y) * self.dpi_scale,
+            Some(matrix) => {
+                let ndc = matrix * vec4(vertex.position.x, vertex.position.y, 0.0, 1.0);
+                vec2(
+                    (ndc.x / ndc.w + 1.0) / 2.0 * self.width as f32,
+                    (1.0 - ndc.y / ndc.w) / 2.0 * self.height as f32,
+                )
+            }
+        }
+    }
+
+    /// Pixels that may be touched, as `(min_x, min_y, max_x, max_y)` with exclusive maximums.
+    fn bounds(&self) -> Bounds {
+        match self.scissor {
+            Some((x0, y0, x1, y1)) => (
+                x0.min(self.width),
+                y0.min(self.height),
+                x1.min(self.width),
+                y1.min(self.height),
+            ),
+            None => (0, 0, self.width, self.height),
+        }
+    }
+
+    fn image_mut(&mut self, texture: &Texture2D) -> Option<&mut Image> {
+        self.textures
+            .iter_mut()
+            .find(|(t, _)| t == texture)
+            .map(|(_, image)| image)
+    }
+
+    fn fill_triangle(&mut self, vertices: [&Vertex; 3], state: &DrawState) {
+        let [a, b, c] = vertices;
+        let points = vertices.map(|v| self.project(v));
+        let (blend, premultiplied) = state.resolved_blend();
+        let image = state.texture.as_ref().and_then(|t| {
+            self.textures
+                .iter()
+                .find(|(texture, _)| texture == t)
+                .map(|(_, image)| image)
+        });
+        let bounds = self.bounds();
+        let width = self.width;
+        let pixels = &mut self.pixels;
+        let stencil = &self.stencil;
+        let stencil_depth = self.stencil_depth;
+        let colors = [a.color, b.color, c.color].map(|c| c.map(|c| c as f32 / 255.0));
+        rasterize(points, bounds, |x, y, w| {
+            let index = y * width + x;
+            if stencil_depth > 0 && stencil[index] != stencil_depth {
+                return;
+            }
+            let mut color = [0.0; 4];
+            for (channel, value) in color.iter_mut().enumerate() {
+                *value = colors[0][channel] * w[0]
+                    + colors[1][channel] * w[1]
+                    + colors[2][channel] * w[2];
+            }
+            if let Some(gradient) = &state.gradient {
+                let uv = a.uv * w[0] + b.uv * w[1] + c.uv * w[2];
+                let texel = gradient.color_at(uv);
+                for (value, texel) in color.iter_mut().zip([texel.r, texel.g, texel.b, texel.a]) {
+                    *value *= texel;
+                }
+            } else if let Some(image) = image {
+                let uv = a.uv * w[0] + b.uv * w[1] + c.uv * w[2];
+                let texel = sample(image, uv, premultiplied, state.wrap);
+                for (value, texel) in color.iter_mut().zip(texel) {
+                    *value *= texel;
+                }
+            }
+            let src = if premultiplied {
+                color
+            } else {
+                premultiply(color)
+            };
+            blend_pixel(&mut pixels[index], src, blend);
+        });
+    }
+
+    /// Sets the stencil to `to` where it equals `from`, inside the triangles.
+    fn update_stencil(&mut self, vertices: &[Vertex], indices: &[u16], from: u8, to: u8) {
+        let bounds = self.bounds();
+        for triangle in indices.chunks_exact(3) {
+            let [Some(a), Some(b), Some(c)] = [0, 1, 2].map(|i| vertices.get(triangle[i] as usize))
+            else {
+                continue;
+            };
+            let points = [a, b, c].map(|v| self.project(v));
+            let width = self.width;
+            let stencil = &mut self.stencil;
+            rasterize(points, bounds, |x, y, _| {
+                let value = &mut stencil[y * width + x];
+                if *value == from {
+                    *value = to;
+                }
+            });
+        }
+    }
+}
+
+/// `(min_x, min_y, max_x, max_y)` in physical pixels, maximums exclusive
+type Bounds = (usize, usize, usize, usize);
+
+/// Calls `f` with the position and barycentric weights of every pixel in `bounds`
+/// whose center is inside the triangle.
+fn rasterize(points: [Vec2; 3], bounds: Bounds, mut f: impl FnMut(usize, usize, [f32; 3])) {
+    let [mut pa, mut pb, pc] = points;
+    let mut area = edge(pa, pb, pc);
+    if area == 0.0 || !area.is_finite() {
+        return;
+    }
+    // Keep the winding consistent so that shared edges are only drawn once
+    let swapped = area < 0.0;
+    if swapped {
+        std::mem::swap(&mut pa, &mut pb);
+        area = -area;
+    }
+    let (bx0, by0, bx1, by1) = bounds;
+    let min_x = (pa.x.min(pb.x).min(pc.x).floor().max(0.0) as usize).max(bx0);
+    let min_y = (pa.y.min(pb.y).min(pc.y).floor().max(0.0) as usize).max(by0);
+    let max_x = (pa.x.max(pb.x).max(pc.x).ceil().max(0.0) as usize).min(bx1);
+    let max_y = (pa.y.max(pb.y).max(pc.y).ceil().max(0.0) as usize).min(by1);
+    let edges = [(pb, pc), (pc, pa), (pa, pb)];
+    for y in min_y..max_y {
+        for x in min_x..max_x {
+            let p = vec2(x as f32 + 0.5, y as f32 + 0.5);
+            let w = edges.map(|(from, to)| edge(from, to, p));
+            if w.iter()
+                .zip(edges)
+                .any(|(w, (from, to))| *w < 0.0 || (*w == 0.0 && !owns_edge(from, to)))
+            {
+                continue;
+            }
+            let [wa, wb, wc] = w.map(|w| w / area);
+            f(x, y, if swapped { [wb, wa, wc] } else { [wa, wb, wc] });
+        }
+    }
+}
+
+fn edge(a: Vec2, b: Vec2, p: Vec2) -> f32 {
+    (b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x)
+}
+
+/// For pixels exactly on an edge shared by two triangles, only one of them draws it.
+fn owns_edge(from: Vec2, to: Vec2) -> bool {
+    to.y > from.y || (to.y == from.y && to.x > from.x)
+}
+
+/// Blends premultiplied colors the way the GL blend states of
+/// [`MacroquadBackend`](super::MacroquadBackend) do.
+fn blend_pixel(dst: &mut [f32; 4], src: [f32; 4], blend: BlendMode) {
+    let src_alpha = src[3];
+    for (channel, (d, s)) in dst.iter_mut().zip(src).enumerate() {
+        let is_alpha = channel == 3;
+        *d = match blend {
+            BlendMode::Normal | BlendMode::Premultiplied => s + *d * (1.0 - src_alpha),
+            BlendMode::Additive => s + *d,
+            BlendMode::Multiply if !is_alpha => s * *d + *d * (1.0 - src_alpha),
+            BlendMode::Screen if !is_alpha => s + *d * (1.0 - s),
+            BlendMode::Multiply | BlendMode::Screen => s + *d * (1.0 - src_alpha),
+        }
+        .clamp(0.0, 1.0);
+    }
+}
+
+fn premultiply([r, g, b, a]: [f32; 4]) -> [f32; 4] {
+    [r * a, g * a, b * a, a]
+}
+
+fn unpremultiply([r, g, b, a]: [f32; 4]) -> [f32; 4] {
+    if a <= 0.0 {
+        [0.0; 4]
+    } else {
+        [r / a, g / a, b / a, a]
+    }
+}
+
+fn to_u8(value: f32) -> u8 {
+    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+/// Bilinear sampling, clamping to edges like macroquad's default textures unless
+/// `wrap` says otherwise.
+///
+/// Premultiplied images are sampled as they are, others are filtered with
+/// premultiplied alpha to avoid dark fringes.
+fn sample(image: &Image, uv: Vec2, premultiplied: bool, wrap: Wrap) -> [f32; 4] {
+    let (width, height) = (image.width as usize, image.height as usize);
+    if width == 0 || height == 0 {
+        return [1.0; 4];
+    }
+    let inside = (0.0..=1.0).contains(&uv.x) && (0.0..=1.0).contains(&uv.y);
+    if wrap == Wrap::Transparent && !inside {
+        return [0.0; 4];
+    }
+    let x = uv.x * width as f32 - 0.5;
+    let y = uv.y * height as f32 - 0.5;
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+    let texel = |x: f32, y: f32| {
+        let (x, y) = (x as isize, y as isize);
+        let (x, y) = if wrap == Wrap::Repeat {
+            (x.rem_euclid(width as isize), y.rem_euclid(height as isize))
+        } else {
+            (
+                x.clamp(0, width as isize - 1),
+                y.clamp(0, height as isize - 1),
+            )
+        };
+        let (x, y) = (x as usize, y as usize);
+        let offset = (y * width + x) * 4;
+        let p = &image.bytes[offset..offset + 4];
+        let texel = [p[0], p[1], p[2], p[3]].map(|c| c as f32 / 255.0);
+        if premultiplied {
+            texel
+        } else {
+            premultiply(texel)
+        }
+    };
+    let [t00, t10, t01, t11] = [
+        texel(x0, y0),
+        texel(x0 + 1.0, y0),
+        texel(x0, y0 + 1.0),
+        texel(x0 + 1.0, y0 + 1.0),
+    ];
+    let mut result = [0.0; 4];
+    for (channel, value) in result.iter_mut().enumerate() {
+        let top = t00[channel] * (1.0 - fx) + t10[channel] * fx;
+        let bottom = t01[channel] * (1.0 - fx) + t11[channel] * fx;
+        *value = top * (1.0 - fy) + bottom * fy;
+    }
+    if premultiplied {
+        result
+    } else {
+        unpremultiply(result)
+    }
+}
+
+impl RenderBackend for SoftwareBackend {
+    fn screen_size(&self) -> (f32, f32) {
+        self.size
+    }
+
+    fn dpi_scale(&self) -> f32 {
+        self.dpi_scale
+    }
+
+    fn max_texture_size(&self) -> u32 {
+        self.max_texture_size
+    }
+
+    fn new_texture(&mut self, width: u32, height: u32) -> Texture2D {
+        self.add_texture(Image {
+            bytes: vec![0; width as usize * height as usize * 4],
+            width: width as u16,
+            height: height as u16,
+        })
+    }
+
+    fn texture_size(&self, texture: &Texture2D) -> Option<(u32, u32)> {
+        self.textures
+            .iter()
+            .find(|(t, _)| t == texture)
+            .map(|(_, image)| (image.width as u32, image.height as u32))
+    }
+
+    fn update_texture(&mut self, texture: &Texture2D, x: i32, y: i32, image: &Image) {
+        let Some(target) = self.image_mut(texture) else {
+            return;
+        };
+        let target_width = target.width as i32;
+        let target_height = target.height as i32;
+        for row in 0..image.height as i32 {
+            for column in 0..image.width as i32 {
+                let (tx, ty) = (x + column, y + row);
+                if tx < 0 || ty < 0 || tx >= target_width || ty >= target_height {
+                    continue;
+                }
+                let src = ((row * image.width as i32 + column) * 4) as usize;
+                let dst = ((ty * target_width + tx) * 4) as usize;
+                target.bytes[dst..dst + 4].copy_from_slice(&image.bytes[src..src + 4]);
+            }
+        }
+    }
+
+    fn set_camera(&mut self, camera: &Camera2D) {
+        self.camera = Some(camera.matrix());
+    }
+
+    fn set_scissor(&mut self, rect: Option<Rect>) {
+        let scale = self.dpi_scale;
+        self.scissor = rect.map(|rect| {
+            let to_pixel = |v: f32| (v * scale).round().max(0.0) as usize;
+            (
+                to_pixel(rect.x),
+                to_pixel(rect.y),
+                to_pixel(rect.right()),
+                to_pixel(rect.bottom()),
+            )
+        });
+    }
+
+    fn push_stencil(&mut self, vertices: &[Vertex], indices: &[u16]) {
+        if self.stencil_depth == u8::MAX {
+            return;
+        }
+        if self.stencil_depth == 0 {
+            self.stencil.fill(0);
+        }
+        self.update_stencil(
+            vertices,
+            indices,
+            self.stencil_depth,
+            self.stencil_depth + 1,
+        );
+        self.stencil_depth += 1;
+    }
+
+    fn pop_stencil(&mut self, vertices: &[Vertex], indices: &[u16]) {
+        if self.stencil_depth == 0 {
+            return;
+        }
+        self.update_stencil(
+            vertices,
+            indices,
+            self.stencil_depth,
+            self.stencil_depth - 1,
+        );
+        self.stencil_depth -= 1;
+    }
+
+    fn push_layer(&mut self) {
+        let (width, height) = (self.width, self.height);
+        let pooled = self.layer_pool.iter().position(|texture| {
+            self.textures.iter().any(|(t, image)| {
+                t == texture && (image.width as usize, image.height as usize) == (width, height)
+            })
+        });
+        let texture = match pooled {
+            Some(index) => self.layer_pool.swap_remove(index),
+            None => self.new_texture(width as u32, height as u32),
+        };
+        let outer = std::mem::replace(&mut self.pixels, vec![[0.0; 4]; width * height]);
+        self.layers.push((outer, texture));
+    }
+
+    fn pop_layer(&mut self) -> Texture2D {
+        let Some((outer, texture)) = self.layers.pop() else {
+            return self.new_texture(1, 1);
+        };
+        let pixels = std::mem::replace(&mut self.pixels, outer);
+        if let Some(image) = self.image_mut(&texture) {
+            image.bytes = pixels.iter().flat_map(|p| p.map(to_u8)).collect();
+        }
+        self.layer_pool.push(texture.clone());
+        texture
+    }
+
+    fn snapshot(&mut self, region: Rect) -> Texture2D {
+        // Only the region is copied, as with GPU backends, so that crops are checked
+        let scale = self.dpi_scale;
+        let x0 = (region.x * scale).floor().max(0.) as usize;
+        let y0 = (region.y * scale).floor().max(0.) as usize;
+        let x1 = (region.right() * scale).ceil().max(0.) as usize;
+        let y1 = (region.bottom() * scale).ceil().max(0.) as usize;
+        let width = self.width;
+        let bytes: Vec<u8> = self
+            .pixels
+            .iter()
+            .enumerate()
+            .flat_map(|(i, p)| {
+                let (x, y) = (i % width, i / width);
+                if (x0..x1).contains(&x) && (y0..y1).contains(&y) {
+                    p.map(to_u8)
+                } else {
+                    [0; 4]
+                }
+            })
+            .collect();
+        let (width, height) = (self.width as u16, self.height as u16);
+        if let Some(texture) = self.snapshot.clone()
+            && let Some(image) = self.image_mut(&texture)
+            && (image.width, image.height) == (width, height)
+        {
+            image.bytes = bytes;
+            return texture;
+        }
+        let texture = self.add_texture(Image {
+            bytes,
+            width,
+            height,
+        });
+        self.snapshot = Some(texture.clone());
+        texture
+    }
+
+    fn read_texture(&mut self, texture: &Texture2D) -> Image {
+        match self.image_mut(texture) {
+            Some(image) => image.clone(),
+            None => Image::empty(),
+        }
+    }
+
+    fn blur(
+        &mut self,
+        texture: &Texture2D,
+        region: Rect,
+        radius: f32,
+        _downsample: u32,
+    ) -> Texture2D {
+        let Some(source) = self.image_mut(texture).map(|image| image.clone()) else {
+            return self.new_texture(1, 1);
+        };
+        let (width, height) = (source.width as usize, source.height as usize);
+        let result = match self.blur_result.clone() {
+            Some(result)
+                if self.image_mut(&result).is_some_and(|image| {
+                    (image.width, image.height) == (source.width, source.height)
+                }) =>
+            {
+                result
+            }
+            _ => {
+                let result = self.new_texture(width as u32, height as u32);
+                self.blur_result = Some(result.clone());
+                result
+            }
+        };
+        let sigma = radius * self.dpi_scale / 2.0;
+        let reach = (sigma * 3.0).ceil() as isize;
+        let kernel: Vec<f32> = (-reach..=reach)
+            .map(|i| {
+                if sigma > 0.0 {
+                    (-((i * i) as f32) / (2.0 * sigma * sigma)).exp()
+                } else {
+                    1.0
+                }
+            })
+            .collect();
+        let total: f32 = kernel.iter().sum();
+        let to_pixel = |v: f32| (v * self.dpi_scale).round().max(0.0) as usize;
+        let (x0, x1) = (
+            to_pixel(region.x).min(width),
+            to_pixel(region.right()).min(width),
+        );
+        let (y0, y1) = (
+            to_pixel(region.y).min(height),
+            to_pixel(region.bottom()).min(height),
+        );
+        // The horizontal pass also covers the rows the vertical one reads
+        let (outer_y0, outer_y1) = (
+            (y0 as isize - reach).max(0) as usize,
+            (y1 as isize + reach).min(height as isize) as usize,
+        );
+        let texel = |x: usize, y: usize| {
+            let offset = (y * width + x) * 4;
+            let p = &source.bytes[offset..offset + 4];
+            [p[0], p[1], p[2], p[3]].map(|c| c as f32 / 255.0)
+        };
+        let mut horizontal = vec![[0.0f32; 4]; width * height];
+        for y in outer_y0..outer_y1 {
+            for x in x0..x1 {
+                let mut sum = [0.0; 4];
+                for (k, weight) in kernel.iter().enumerate() {
+                    let sx = x as isize + k as isize - reach;
+                    if sx < 0 || sx >= width as isize {
+                        continue;
+                    }
+                    for (s, t) in sum.iter_mut().zip(texel(sx as usize, y)) {
+                        *s += t * weight;
+                    }
+                }
+                horizontal[y * width + x] = sum.map(|s| s / total);
+            }
+        }
+        let mut bytes = vec![0; width * height * 4];
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let mut sum = [0.0; 4];
+                for (k, weight) in kernel.iter().enumerate() {
+                    let sy = y as isize + k as isize - reach;
+                    if sy < 0 || sy >= height as isize {
+                        continue;
+                    }
+                    for (s, t) in sum.iter_mut().zip(horizontal[sy as usize * width + x]) {
+                        *s += t * weight;
+                    }
+                }
+                let offset = (y * width + x) * 4;
+                for (byte, s) in bytes[offset..offset + 4].iter_mut().zip(sum) {
+                    *byte = to_u8(s / total);
+                }
+            }
+        }
+        if let Some(image) = self.image_mut(&result) {
+            image.bytes = bytes;
+        }
+        result
+    }
+
+    fn draw_triangles(&mut self, vertices: &[Vertex], indices: &[u16], state: &DrawState) {
+        for triangle in indices.chunks_exact(3) {
+            let [Some(a), Some(b), Some(c)] = [0, 1, 2].map(|i| vertices.get(triangle[i] as usize))
+            else {
+                continue;
+            };
+            self.fill_triangle([a, b, c], state);
+        }
+    }
+}
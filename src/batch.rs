@@ -0,0 +1,190 @@
+//! Merges consecutive draws sharing the same [`DrawState`] into one backend submission.
+
+use std::collections::HashMap;
+
+use macroquad::{texture::Texture2D, ui::Vertex};
+
+use crate::{
+    backend::RenderBackend,
+    material::MaterialState,
+    shading::{Gradient, Shading, Wrap},
+    stats::FrameStats,
+};
+
+/// How drawn colors are combined with what is already on the target.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum BlendMode {
+    /// Regular alpha blending.
+    #[default]
+    Normal,
+    /// Adds the color on top, for glows and highlights.
+    Additive,
+    /// Darkens the target by multiplying it with the color.
+    Multiply,
+    /// Brightens the target, the inverse of [`BlendMode::Multiply`].
+    Screen,
+    /// Regular alpha blending of colors that are already multiplied by their alpha.
+    Premultiplied,
+}
+
+/// Everything besides the geometry that decides how triangles are drawn.
+///
+/// Draws can only be batched together if their states are equal.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DrawState {
+    pub texture: Option<Texture2D>,
+    pub blend: BlendMode,
+    /// Vertex colors and texture are already multiplied by their alpha,
+    /// as is the case for offscreen layers.
+    pub premultiplied: bool,
+    /// `None` for the default shader
+    pub material: Option<MaterialState>,
+    /// Colors the geometry instead of the texture, see [`Shading::gradient`]
+    pub gradient: Option<Gradient>,
+    pub wrap: Wrap,
+}
+
+impl DrawState {
+    /// What `shading` draws with.
+    pub fn of(shading: &impl Shading) -> Self {
+        Self {
+            texture: shading.texture(),
+            blend: shading.blend_mode(),
+            premultiplied: false,
+            material: shading.material(),
+            gradient: shading.gradient(),
+            wrap: shading.wrap(),
+        }
+    }
+
+    /// The blend equation to use and whether the source colors are premultiplied.
+    ///
+    /// The returned mode is never [`BlendMode::Premultiplied`], which is
+    /// [`BlendMode::Normal`] with premultiplied colors.
+    pub fn resolved_blend(&self) -> (BlendMode, bool) {
+        match self.blend {
+            BlendMode::Premultiplied => (BlendMode::Normal, true),
+            blend => (blend, self.premultiplied),
+        }
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct Batcher {
+    state: DrawState,
+    vertices: Vec<Vertex>,
+    indices: Vec<u16>,
+}
+
+impl Batcher {
+    /// Appends the geometry to the current batch, flushing it first if
+    /// the state changes or the batch would grow too large for the backend.
+    ///
+    /// Geometry too large for a single submission is split into several.
+    pub fn push(
+        &mut self,
+        backend: &mut dyn RenderBackend,
+        stats: &mut FrameStats,
+        state: &DrawState,
+        vertices: &[Vertex],
+        indices: &[u16],
+    ) {
+        let (max_vertices, max_indices) = submission_limits(backend);
+        if vertices.len() > max_vertices || indices.len() > max_indices {
+            split_mesh(
+                vertices,
+                indices,
+                (max_vertices, max_indices),
+                |vertices, indices| self.push(backend, stats, state, vertices, indices),
+            );
+            return;
+        }
+        if self.state != *state
+            || self.vertices.len() + vertices.len() > max_vertices
+            || self.indices.len() + indices.len() > max_indices
+        {
+            self.flush(backend, stats);
+            self.state = state.clone();
+        }
+        let offset = self.vertices.len() as u16;
+        self.vertices.extend_from_slice(vertices);
+        self.indices.extend(indices.iter().map(|i| i + offset));
+    }
+
+    pub fn flush(&mut self, backend: &mut dyn RenderBackend, stats: &mut FrameStats) {
+        if !self.indices.is_empty() {
+            draw(backend, stats, &self.vertices, &self.indices, &self.state);
+        }
+        self.vertices.clear();
+        self.indices.clear();
+    }
+
+    pub fn state(&self) -> &DrawState {
+        &self.state
+    }
+}
+
+/// Largest number of vertices and indices that fit in one submission to `backend`,
+/// indices being a whole number of triangles.
+///
+/// At least one triangle, and no more vertices than `u16` indices can address.
+pub(crate) fn submission_limits(backend: &dyn RenderBackend) -> (usize, usize) {
+    let (vertices, indices) = backend.max_submission_size();
+    (
+        vertices.clamp(3, u16::MAX as usize + 1),
+        (indices.max(3) / 3) * 3,
+    )
+}
+
+/// Splits a mesh into parts of at most `max_vertices` vertices and `max_indices` indices,
+/// skipping triangles that refer to vertices that don't exist.
+pub(crate) fn split_mesh<I: Copy + Into<u32>>(
+    vertices: &[Vertex],
+    indices: &[I],
+    (max_vertices, max_indices): (usize, usize),
+    mut emit: impl FnMut(&[Vertex], &[u16]),
+) {
+    let mut part_vertices = Vec::new();
+    let mut part_indices = Vec::new();
+    // Index in `vertices` -> index in `part_vertices`
+    let mut remap: HashMap<u32, u16> = HashMap::new();
+    for triangle in indices.chunks_exact(3) {
+        let triangle = [triangle[0].into(), triangle[1].into(), triangle[2].into()];
+        if triangle.iter().any(|&i| i as usize >= vertices.len()) {
+            continue;
+        }
+        let new_vertices = triangle.iter().filter(|i| !remap.contains_key(i)).count();
+        if !part_indices.is_empty()
+            && (part_vertices.len() + new_vertices > max_vertices
+                || part_indices.len() + 3 > max_indices)
+        {
+            emit(&part_vertices, &part_indices);
+            part_vertices.clear();
+            part_indices.clear();
+            remap.clear();
+        }
+        for i in triangle {
+            let index = *remap.entry(i).or_insert_with(|| {
+                part_vertices.push(vertices[i as usize]);
+                (part_vertices.len() - 1) as u16
+            });
+            part_indices.push(index);
+        }
+    }
+    if !part_indices.is_empty() {
+        emit(&part_vertices, &part_indices);
+    }
+}
+
+fn draw(
+    backend: &mut dyn RenderBackend,
+    stats: &mut FrameStats,
+    vertices: &[Vertex],
+    indices: &[u16],
+    state: &DrawState,
+) {
+    stats.draw_calls += 1;
+    stats.vertices += vertices.len();
+    stats.indices += indices.len();
+    backend.draw_triangles(vertices, indices, state);
+}
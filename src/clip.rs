@@ -0,0 +1,52 @@
+use lyon::{
+    algorithms::hit_test::hit_test_path,
+    math::{Box2D, point},
+    path::{FillRule, Path},
+};
+
+use crate::utils::Point;
+
+/// A clipping region, in the local coordinate system of a component.
+#[derive(Clone, Debug)]
+pub enum Clip {
+    /// Clipping to a rectangle is cheap as long as the transform does not rotate or shear it.
+    Rect(Box2D),
+    /// Any other shape, filled with the non-zero rule.
+    Path(Path),
+}
+
+impl Clip {
+    /// The whole component, from (-0.5, -0.5) to (0.5, 0.5).
+    pub fn unit() -> Self {
+        Clip::Rect(Box2D::new(point(-0.5, -0.5), point(0.5, 0.5)))
+    }
+
+    /// `p` is in the same coordinate system as the clip.
+    pub fn contains(&self, p: &Point) -> bool {
+        match self {
+            Clip::Rect(rect) => {
+                rect.min.x <= p.x && p.x <= rect.max.x && rect.min.y <= p.y && p.y <= rect.max.y
+            }
+            Clip::Path(path) => hit_test_path(
+                &point(p.x, p.y),
+                path.iter(),
+                FillRule::NonZero,
+                Self::HIT_TEST_TOLERANCE,
+            ),
+        }
+    }
+
+    const HIT_TEST_TOLERANCE: f32 = 1e-3;
+}
+
+impl From<Box2D> for Clip {
+    fn from(rect: Box2D) -> Self {
+        Clip::Rect(rect)
+    }
+}
+
+impl From<Path> for Clip {
+    fn from(path: Path) -> Self {
+        Clip::Path(path)
+    }
+}
@@ -0,0 +1,450 @@
+//! Color conversions, parsing and manipulation for macroquad's [`Color`], see [`ColorExt`].
+//!
+//! [`Color`] holds sRGB components in 0..=1, not premultiplied.
+
+use anyhow::Context;
+use macroquad::color::Color;
+
+/// Where colors are interpolated, for mixing and gradients.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ColorSpace {
+    /// The components as they are, like SVG and CSS gradients
+    #[default]
+    Srgb,
+    /// Physically linear light, brighter in the middle than sRGB
+    LinearRgb,
+    /// Perceptually uniform, without the gray band between complementary colors
+    Oklab,
+}
+
+/// sRGB with the transfer function undone.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LinearRgb {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32,
+}
+
+/// Hue in degrees, saturation and lightness in 0..=1.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Hsl {
+    pub h: f32,
+    pub s: f32,
+    pub l: f32,
+    pub a: f32,
+}
+
+/// Björn Ottosson's perceptual color space, lightness in 0..=1.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Oklab {
+    pub l: f32,
+    pub a: f32,
+    pub b: f32,
+    pub alpha: f32,
+}
+
+/// [`Oklab`] in polar coordinates, hue in degrees.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Oklch {
+    pub l: f32,
+    pub c: f32,
+    pub h: f32,
+    pub alpha: f32,
+}
+
+fn srgb_to_linear(c: f32) -> f32 {
+    let x = c.abs();
+    let x = if x <= 0.04045 {
+        x / 12.92
+    } else {
+        ((x + 0.055) / 1.055).powf(2.4)
+    };
+    x.copysign(c)
+}
+
+fn linear_to_srgb(c: f32) -> f32 {
+    let x = c.abs();
+    let x = if x <= 0.0031308 {
+        x * 12.92
+    } else {
+        1.055 * x.powf(1. / 2.4) - 0.055
+    };
+    x.copysign(c)
+}
+
+/// Colors out of the sRGB gamut are clipped component-wise.
+fn clamped(r: f32, g: f32, b: f32, a: f32) -> Color {
+    Color::new(
+        r.clamp(0., 1.),
+        g.clamp(0., 1.),
+        b.clamp(0., 1.),
+        a.clamp(0., 1.),
+    )
+}
+
+impl From<Color> for LinearRgb {
+    fn from(color: Color) -> Self {
+        Self {
+            r: srgb_to_linear(color.r),
+            g: srgb_to_linear(color.g),
+            b: srgb_to_linear(color.b),
+            a: color.a,
+        }
+    }
+}
+
+impl From<LinearRgb> for Color {
+    fn from(color: LinearRgb) -> Self {
+        clamped(
+            linear_to_srgb(color.r),
+            linear_to_srgb(color.g),
+            linear_to_srgb(color.b),
+            color.a,
+        )
+    }
+}
+
+impl From<Color> for Hsl {
+    fn from(color: Color) -> Self {
+        let Color { r, g, b, a } = color;
+        let max = r.max(g).max(b);
+        let min = r.min(g).min(b);
+        let l = (max + min) / 2.;
+        let d = max - min;
+        if d <= 0. {
+            return Self { h: 0., s: 0., l, a };
+        }
+        let s = d / (1. - (2. * l - 1.).abs());
+        let h = if max == r {
+            ((g - b) / d).rem_euclid(6.)
+        } else if max == g {
+            (b - r) / d + 2.
+        } else {
+            (r - g) / d + 4.
+        };
+        Self {
+            h: h * 60.,
+            s,
+            l,
+            a,
+        }
+    }
+}
+
+impl From<Hsl> for Color {
+    fn from(hsl: Hsl) -> Self {
+        let Hsl { h, s, l, a } = hsl;
+        let c = (1. - (2. * l - 1.).abs()) * s;
+        let h = h.rem_euclid(360.) / 60.;
+        let x = c * (1. - (h.rem_euclid(2.) - 1.).abs());
+        let (r, g, b) = match h as u32 {
+            0 => (c, x, 0.),
+            1 => (x, c, 0.),
+            2 => (0., c, x),
+            3 => (0., x, c),
+            4 => (x, 0., c),
+            _ => (c, 0., x),
+        };
+        let m = l - c / 2.;
+        clamped(r + m, g + m, b + m, a)
+    }
+}
+
+impl From<LinearRgb> for Oklab {
+    fn from(color: LinearRgb) -> Self {
+        let LinearRgb { r, g, b, a } = color;
+        let l = (0.412_221_46 * r + 0.536_332_55 * g + 0.051_445_995 * b).cbrt();
+        let m = (0.211_903_5 * r + 0.680_699_5 * g + 0.107_396_96 * b).cbrt();
+        let s = (0.088_302_46 * r + 0.281_718_85 * g + 0.629_978_7 * b).cbrt();
+        Self {
+            l: 0.210_454_26 * l + 0.793_617_8 * m - 0.004_072_047 * s,
+            a: 1.977_998_5 * l - 2.428_592_2 * m + 0.450_593_7 * s,
+            b: 0.025_904_037 * l + 0.782_771_77 * m - 0.808_675_77 * s,
+            alpha: a,
+        }
+    }
+}
+
+impl From<Oklab> for LinearRgb {
+    fn from(color: Oklab) -> Self {
+        let Oklab { l, a, b, alpha } = color;
+        let l_ = l + 0.396_337_78 * a + 0.215_803_76 * b;
+        let m_ = l - 0.105_561_346 * a - 0.063_854_17 * b;
+        let s_ = l - 0.089_484_18 * a - 1.291_485_5 * b;
+        let (l, m, s) = (l_ * l_ * l_, m_ * m_ * m_, s_ * s_ * s_);
+        Self {
+            r: 4.076_741_7 * l - 3.307_711_6 * m + 0.230_969_94 * s,
+            g: -1.268_438 * l + 2.609_757_4 * m - 0.341_319_38 * s,
+            b: -0.004_196_086_3 * l - 0.703_418_6 * m + 1.707_614_7 * s,
+            a: alpha,
+        }
+    }
+}
+
+impl From<Color> for Oklab {
+    fn from(color: Color) -> Self {
+        LinearRgb::from(color).into()
+    }
+}
+
+impl From<Oklab> for Color {
+    fn from(color: Oklab) -> Self {
+        LinearRgb::from(color).into()
+    }
+}
+
+impl From<Oklab> for Oklch {
+    fn from(color: Oklab) -> Self {
+        Self {
+            l: color.l,
+            c: color.a.hypot(color.b),
+            h: color.b.atan2(color.a).to_degrees().rem_euclid(360.),
+            alpha: color.alpha,
+        }
+    }
+}
+
+impl From<Oklch> for Oklab {
+    fn from(color: Oklch) -> Self {
+        let (sin, cos) = color.h.to_radians().sin_cos();
+        Self {
+            l: color.l,
+            a: color.c * cos,
+            b: color.c * sin,
+            alpha: color.alpha,
+        }
+    }
+}
+
+impl From<Color> for Oklch {
+    fn from(color: Color) -> Self {
+        Oklab::from(color).into()
+    }
+}
+
+impl From<Oklch> for Color {
+    fn from(color: Oklch) -> Self {
+        Oklab::from(color).into()
+    }
+}
+
+/// Parses a CSS color: `#rgb`, `#rgba`, `#rrggbb`, `#rrggbbaa`, `rgb()`, `rgba()`,
+/// `hsl()`, `hsla()`, `transparent` or a named color.
+pub fn parse(text: &str) -> anyhow::Result<Color> {
+    let color: svgtypes::Color = text
+        .trim()
+        .parse()
+        .with_context(|| format!("invalid color {text:?}"))?;
+    Ok(from_rgba8([
+        color.red,
+        color.green,
+        color.blue,
+        color.alpha,
+    ]))
+}
+
+pub fn from_rgba8([r, g, b, a]: [u8; 4]) -> Color {
+    Color::from_rgba(r, g, b, a)
+}
+
+/// Components rounded to the nearest byte, clamped to 0..=255.
+pub fn to_rgba8(color: Color) -> [u8; 4] {
+    [color.r, color.g, color.b, color.a].map(|c| (c.clamp(0., 1.) * 255.).round() as u8)
+}
+
+/// Manipulation of macroquad's [`Color`].
+pub trait ColorExt: Sized {
+    /// Raises the [`Oklch`] lightness by `amount`, in 0..=1.
+    fn lighten(self, amount: f32) -> Self;
+
+    /// Lowers the [`Oklch`] lightness by `amount`, in 0..=1.
+    fn darken(self, amount: f32) -> Self {
+        self.lighten(-amount)
+    }
+
+    /// `self` at `t` = 0 to `other` at `t` = 1, interpolated in `space`.
+    fn mix(self, other: Self, t: f32, space: ColorSpace) -> Self;
+
+    fn with_alpha(self, alpha: f32) -> Self;
+
+    /// `#rrggbbaa`, `#rrggbb` if opaque.
+    fn to_hex(self) -> String;
+}
+
+impl ColorExt for Color {
+    fn lighten(self, amount: f32) -> Self {
+        let mut lch = Oklch::from(self);
+        lch.l = (lch.l + amount).clamp(0., 1.);
+        lch.into()
+    }
+
+    fn mix(self, other: Self, t: f32, space: ColorSpace) -> Self {
+        let lerp = |from: f32, to: f32| from + (to - from) * t;
+        match space {
+            ColorSpace::Srgb => Color::new(
+                lerp(self.r, other.r),
+                lerp(self.g, other.g),
+                lerp(self.b, other.b),
+                lerp(self.a, other.a),
+            ),
+            ColorSpace::LinearRgb => {
+                let (from, to) = (LinearRgb::from(self), LinearRgb::from(other));
+                LinearRgb {
+                    r: lerp(from.r, to.r),
+                    g: lerp(from.g, to.g),
+                    b: lerp(from.b, to.b),
+                    a: lerp(from.a, to.a),
+                }
+                .into()
+            }
+            ColorSpace::Oklab => {
+                let (from, to) = (Oklab::from(self), Oklab::from(other));
+                Oklab {
+                    l: lerp(from.l, to.l),
+                    a: lerp(from.a, to.a),
+                    b: lerp(from.b, to.b),
+                    alpha: lerp(from.alpha, to.alpha),
+                }
+                .into()
+            }
+        }
+    }
+
+    fn with_alpha(mut self, alpha: f32) -> Self {
+        self.a = alpha;
+        self
+    }
+
+    fn to_hex(self) -> String {
+        let [r, g, b, a] = to_rgba8(self);
+        if a == 255 {
+            format!("#{r:02x}{g:02x}{b:02x}")
+        } else {
+            format!("#{r:02x}{g:02x}{b:02x}{a:02x}")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f32 = 1e-4;
+
+    fn samples() -> Vec<Color> {
+        let mut colors = vec![
+            Color::new(0., 0., 0., 1.),
+            Color::new(1., 1., 1., 1.),
+            Color::new(0.5, 0.5, 0.5, 0.5),
+            Color::new(1., 0., 0., 1.),
+            Color::new(0., 1., 0., 0.),
+            Color::new(0., 0., 1., 1.),
+        ];
+        for i in 0..27 {
+            let c = |n: u32| (n % 3) as f32 * 0.45 + 0.05;
+            colors.push(Color::new(c(i), c(i / 3), c(i / 9), 0.8));
+        }
+        colors
+    }
+
+    fn assert_close(a: Color, b: Color) {
+        let close = [a.r - b.r, a.g - b.g, a.b - b.b, a.a - b.a]
+            .iter()
+            .all(|d| d.abs() < EPSILON);
+        assert!(close, "{a:?} != {b:?}");
+    }
+
+    #[test]
+    fn round_trips() {
+        for color in samples() {
+            assert_close(LinearRgb::from(color).into(), color);
+            assert_close(Hsl::from(color).into(), color);
+            assert_close(Oklab::from(color).into(), color);
+            assert_close(Oklch::from(color).into(), color);
+        }
+    }
+
+    #[test]
+    fn known_values() {
+        let red = Color::new(1., 0., 0., 1.);
+        let hsl = Hsl::from(red);
+        assert_eq!((hsl.h, hsl.s, hsl.l), (0., 1., 0.5));
+        assert!((LinearRgb::from(Color::new(0.5, 0.5, 0.5, 1.)).r - 0.214_041).abs() < EPSILON);
+        let white = Oklab::from(Color::new(1., 1., 1., 1.));
+        assert!((white.l - 1.).abs() < EPSILON);
+        assert!(white.a.abs() < EPSILON && white.b.abs() < EPSILON);
+        // Red is at about 29° in Oklch
+        assert!((Oklch::from(red).h - 29.23).abs() < 0.1);
+    }
+
+    #[test]
+    fn parses_colors() {
+        assert_close(parse("#ff8000").unwrap(), from_rgba8([255, 128, 0, 255]));
+        assert_close(parse("#f80").unwrap(), from_rgba8([255, 136, 0, 255]));
+        assert_close(
+            parse(" #ff800080 ").unwrap(),
+            from_rgba8([255, 128, 0, 128]),
+        );
+        assert_close(parse("rgb(0, 0, 255)").unwrap(), Color::new(0., 0., 1., 1.));
+        assert_close(
+            parse("hsl(120, 100%, 50%)").unwrap(),
+            Color::new(0., 1., 0., 1.),
+        );
+        assert_close(parse("white").unwrap(), Color::new(1., 1., 1., 1.));
+        assert_eq!(parse("transparent").unwrap().a, 0.);
+        for bad in ["", "#12", "#ggg", "rgb(1, 2)", "no-such-color"] {
+            let error = parse(bad).unwrap_err();
+            assert!(format!("{error}").contains("invalid color"), "{bad:?}");
+        }
+    }
+
+    #[test]
+    fn formats_hex() {
+        assert_eq!(Color::new(1., 0.5, 0., 1.).to_hex(), "#ff8000");
+        assert_eq!(Color::new(0., 0., 0., 0.).to_hex(), "#00000000");
+        // Out of range components are clamped
+        assert_eq!(Color::new(2., -1., 0., 1.).to_hex(), "#ff0000");
+        for hex in ["#12345678", "#abcdef"] {
+            assert_eq!(parse(hex).unwrap().to_hex(), hex);
+        }
+    }
+
+    #[test]
+    fn mixes_in_each_space() {
+        let black = Color::new(0., 0., 0., 1.);
+        let white = Color::new(1., 1., 1., 0.);
+        for space in [ColorSpace::Srgb, ColorSpace::LinearRgb, ColorSpace::Oklab] {
+            assert_close(black.mix(white, 0., space), black);
+            assert_close(black.mix(white, 1., space), white);
+            assert!((black.mix(white, 0.5, space).a - 0.5).abs() < EPSILON);
+        }
+        let gray = |space| black.mix(white, 0.5, space).r;
+        assert!((gray(ColorSpace::Srgb) - 0.5).abs() < EPSILON);
+        // Linear light is brighter in the middle, Oklab is halfway in lightness
+        assert!((gray(ColorSpace::LinearRgb) - 0.735_357).abs() < 1e-3);
+        let middle = Oklab::from(black.mix(white, 0.5, ColorSpace::Oklab));
+        assert!((middle.l - 0.5).abs() < 1e-3);
+
+        // Complementary colors don't pass through gray in Oklab
+        let (blue, yellow) = (Color::new(0., 0., 1., 1.), Color::new(1., 1., 0., 1.));
+        let srgb = Oklch::from(blue.mix(yellow, 0.5, ColorSpace::Srgb));
+        let oklab = Oklch::from(blue.mix(yellow, 0.5, ColorSpace::Oklab));
+        assert!(srgb.c < 1e-3);
+        assert!(oklab.l > srgb.l);
+    }
+
+    #[test]
+    fn converts_to_cosmic_colors_in_order() {
+        use crate::utils::{cosmic_color_to_macroquad_color, macroquad_color_to_cosmic_color};
+
+        let color = from_rgba8([10, 20, 30, 40]);
+        let cosmic = macroquad_color_to_cosmic_color(color);
+        assert_eq!(
+            (cosmic.r(), cosmic.g(), cosmic.b(), cosmic.a()),
+            (10, 20, 30, 40)
+        );
+        assert_eq!(cosmic.as_rgba(), [10, 20, 30, 40]);
+        assert_close(cosmic_color_to_macroquad_color(cosmic), color);
+    }
+}
@@ -1,4 +1,7 @@
-use crate::{utils::Transform, window::Window};
+use crate::{
+    utils::Transform,
+    window::{ScreenMetrics, Window},
+};
 
 pub trait Component {
     /// Returns `Ok(true)` if the event was consumed, `Ok(false)` if not. `Err` is for errors.
@@ -7,4 +10,17 @@ pub trait Component {
     #[allow(unused_variables)]
     /// `tr`: (x_comp, y_comp) -> (x_global, y_global)
     fn render(&mut self, tr: &Transform, target: &mut Window) {}
+
+    #[allow(unused_variables)]
+    /// Called when the size or DPI scale of the screen changed, see [`Window::update`].
+    fn resize(&mut self, metrics: &ScreenMetrics) {}
+
+    /// Whether rendering again would draw anything different from the last render,
+    /// so that [`Retained`](crate::retained::Retained) components know when to redraw.
+    ///
+    /// Only components tracking their own state can tell, so this is true by default.
+    /// If the root of the tree is clean, the app can skip drawing the frame altogether.
+    fn is_dirty(&mut self) -> bool {
+        true
+    }
 }
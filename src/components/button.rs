@@ -66,6 +66,11 @@ impl Component for QuadButton {
         Ok(should_consume)
     }
 
+    /// Only the pressed state is drawn by the button itself.
+    fn is_dirty(&mut self) -> bool {
+        self.pressed != self.touch_id.is_some()
+    }
+
     fn render(&mut self, _tr: &Transform, _target: &mut Window) {
         let touching = self.touch_id.is_some();
         if self.pressed != touching {
@@ -0,0 +1,52 @@
+use std::sync::Arc;
+
+use macroquad::color::Color;
+
+use crate::{component::Component, svg::Svg, utils::Transform, window::Window};
+
+/// An SVG icon.
+///
+/// The document is stretched over (-0.5, -0.5) to (0.5, 0.5) in its local coordinate system.
+pub struct Icon {
+    pub svg: Arc<Svg>,
+    /// Paints the whole icon in this color, keeping its opacities.
+    pub color: Option<Color>,
+    /// What the last render drew
+    rendered: Option<(Arc<Svg>, Option<Color>)>,
+}
+
+impl Icon {
+    pub fn new(svg: Arc<Svg>) -> Self {
+        Self {
+            svg,
+            color: None,
+            rendered: None,
+        }
+    }
+
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = Some(color);
+        self
+    }
+}
+
+impl Component for Icon {
+    fn touch(&mut self, _touch: &macroquad::prelude::Touch) -> anyhow::Result<bool> {
+        Ok(false)
+    }
+
+    fn render(&mut self, tr: &Transform, target: &mut Window) {
+        let (width, height) = self.svg.size;
+        let svg_tr = tr
+            * Transform::new_translation(&nalgebra::Vector2::new(-0.5, -0.5))
+            * Transform::new_nonuniform_scaling(&nalgebra::Vector2::new(1. / width, 1. / height));
+        self.svg.render(&svg_tr, target, self.color);
+        self.rendered = Some((self.svg.clone(), self.color));
+    }
+
+    fn is_dirty(&mut self) -> bool {
+        self.rendered
+            .as_ref()
+            .is_none_or(|(svg, color)| !Arc::ptr_eq(svg, &self.svg) || *color != self.color)
+    }
+}
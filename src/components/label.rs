@@ -3,18 +3,23 @@ use std::hash::{DefaultHasher, Hash, Hasher};
 use cosmic_text::{Attrs, Buffer, Metrics, Shaping};
 use macroquad::{
     color::Color,
-    math::vec2,
+    math::{Rect, vec2},
     prelude::warn,
-    texture::{DrawTextureParams, draw_texture_ex},
 };
 use tracing::{Level, instrument, span};
 
-use crate::{utils::Point, window::Window};
+use crate::{
+    rich_text::{Span, parse_markup},
+    utils::{Point, cosmic_color_to_macroquad_color},
+    window::{ScreenMetrics, Window},
+};
 
 pub use cosmic_text::Align;
 
 pub struct Label {
     pub text: String,
+    /// Styled runs of text, shown instead of [`Self::text`] unless empty.
+    pub spans: Vec<Span>,
     pub font_size: f32,
     pub line_height: f32,
     /// The width for the area to show the label.
@@ -31,6 +36,8 @@ pub struct Label {
     /// The alignment of the text.
     pub text_align: Align,
     cached_buffer: Option<(u64, Buffer, (f32, f32))>,
+    /// Color and texture alignment of the last render, which the buffer doesn't depend on
+    rendered: Option<(Color, (f32, f32))>,
 }
 
 #[derive(Hash)]
@@ -50,12 +57,14 @@ impl Default for Label {
     fn default() -> Self {
         Self {
             text: String::new(),
+            spans: Vec::new(),
             font_size: 16.,
             line_height: 20.,
             area_height: None,
             area_width: None,
             color: Color::from_rgba(255, 255, 255, 255), // Default white color
             cached_buffer: None,
+            rendered: None,
             text_align: Align::Left,
             texture_align: (0.5, 0.5),
         }
@@ -69,6 +78,27 @@ impl Label {
         }
     }
 
+    pub fn with_spans(mut self, spans: impl Into<Vec<Span>>) -> Self {
+        self.spans = spans.into();
+        self
+    }
+
+    /// Shows `markup` in the styles of its tags, see [`parse_markup`].
+    /// Invalid markup is shown as it is.
+    pub fn with_markup(mut self, markup: &str) -> Self {
+        if let Err(e) = self.set_markup(markup) {
+            warn!("Invalid markup: {:?}", e);
+            self.spans = vec![Span::new(markup)];
+        }
+        self
+    }
+
+    /// Replaces the spans with those of `markup`, see [`parse_markup`].
+    pub fn set_markup(&mut self, markup: &str) -> anyhow::Result<()> {
+        self.spans = parse_markup(markup)?;
+        Ok(())
+    }
+
     pub fn with_font_size(mut self, font_size: f32) -> Self {
         self.font_size = font_size;
         self
@@ -116,52 +146,59 @@ impl Label {
                     None
                 }
             })
-            .unwrap_or(self.layout_text(target));
+            .unwrap_or_else(|| self.layout_text(target));
         let span = span!(Level::DEBUG, "Draw buffers");
         let _enter = span.enter();
         let text_block = {
             let (w, h) = buffer.size();
             vec2(
-                w.unwrap_or(text_block_w) / target.logical_ppi,
-                h.unwrap_or(text_block_h) / target.logical_ppi,
+                w.unwrap_or(text_block_w) / target.dpi_scale(),
+                h.unwrap_or(text_block_h) / target.dpi_scale(),
             )
         };
         for run in buffer.layout_runs() {
             for glyph in run.glyphs.iter() {
                 let physical_glyph = glyph.physical((0., 0.), 1.0);
                 // cache if needed
-                target.font_atlas.cache_glyph(
-                    physical_glyph.cache_key,
-                    &mut target.swash_cache,
-                    &mut target.font_system,
-                );
-                let rect = target
-                    .font_atlas
-                    .get_glyph(physical_glyph.cache_key)
-                    .unwrap();
-                let placement = target
-                    .font_atlas
-                    .get_placement(physical_glyph.cache_key)
-                    .unwrap();
+                let Some((rect, placement)) = target.cache_glyph(physical_glyph.cache_key) else {
+                    continue;
+                };
                 let target_size =
-                    vec2(placement.width as f32, placement.height as f32) / target.logical_ppi;
-                draw_texture_ex(
-                    &target.font_atlas.texture,
-                    (physical_glyph.x + placement.left) as f32 / target.logical_ppi + origin.x
-                        - self.texture_align.0 * text_block.x,
-                    ((physical_glyph.y - placement.top) as f32 + run.line_y) / target.logical_ppi
-                        + origin.y
-                        - self.texture_align.1 * text_block.y,
-                    self.color,
-                    DrawTextureParams {
-                        dest_size: Some(target_size),
-                        source: Some(rect),
-                        ..Default::default()
-                    },
+                    vec2(placement.width as f32, placement.height as f32) / target.dpi_scale();
+                let atlas_length = target.font_atlas.length() as f32;
+                let texture = target.font_atlas.texture.clone();
+                let color = match glyph.color_opt {
+                    Some(color) => {
+                        let mut color = cosmic_color_to_macroquad_color(color);
+                        color.a *= self.color.a;
+                        color
+                    }
+                    None => self.color,
+                };
+                target.draw_texture_rect(
+                    &texture,
+                    Rect::new(
+                        (physical_glyph.x + placement.left) as f32 / target.dpi_scale() + origin.x
+                            - self.texture_align.0 * text_block.x,
+                        ((physical_glyph.y - placement.top) as f32 + run.line_y)
+                            / target.dpi_scale()
+                            + origin.y
+                            - self.texture_align.1 * text_block.y,
+                        target_size.x,
+                        target_size.y,
+                    ),
+                    Rect::new(
+                        rect.x / atlas_length,
+                        rect.y / atlas_length,
+                        rect.w / atlas_length,
+                        rect.h / atlas_length,
+                    ),
+                    color,
                 );
             }
         }
         self.cached_buffer = Some((hash, buffer, (text_block_w, text_block_h)));
+        self.rendered = Some((self.color, self.texture_align));
     }
 
     pub fn latest_layout(&mut self, target: &mut Window) -> &mut Buffer {
@@ -175,7 +212,7 @@ impl Label {
                         None
                     }
                 })
-                .unwrap_or(self.layout_text(target)),
+                .unwrap_or_else(|| self.layout_text(target)),
         );
         &mut self.cached_buffer.as_mut().unwrap().1
     }
@@ -190,8 +227,10 @@ impl Label {
     /// - `Buffer`: the cosmic text buffer containing the text layout
     /// - `(f32, f32)`: the width and height of the text block in pixels
     fn layout_text(&self, target: &mut Window) -> (u64, Buffer, (f32, f32)) {
+        target.count_label_layout();
+        let dpi_scale = target.dpi_scale();
         let metrics = Metrics::relative(
-            self.font_size * target.logical_ppi,
+            self.font_size * dpi_scale,
             self.line_height / self.font_size,
         );
         let font_system = &mut target.font_system;
@@ -200,18 +239,29 @@ impl Label {
         let mut buffer_borrowed = buffer.borrow_with(font_system);
         // Set a size for the text buffer, in pixels
         buffer_borrowed.set_size(
-            self.area_width.map(|w| w * target.logical_ppi),
-            self.area_height.map(|h| h * target.logical_ppi),
+            self.area_width.map(|w| w * dpi_scale),
+            self.area_height.map(|h| h * dpi_scale),
         );
         // Attributes indicate what font to choose
         let attrs = Attrs::new();
         // Add some text!
-        buffer_borrowed.set_rich_text(
-            [(self.text.as_str(), attrs.clone())],
-            &attrs,
-            Shaping::Advanced,
-            Some(self.text_align),
-        );
+        if self.spans.is_empty() {
+            buffer_borrowed.set_rich_text(
+                [(self.text.as_str(), attrs.clone())],
+                &attrs,
+                Shaping::Advanced,
+                Some(self.text_align),
+            );
+        } else {
+            buffer_borrowed.set_rich_text(
+                self.spans
+                    .iter()
+                    .map(|span| (span.text.as_str(), span.attrs(metrics, dpi_scale))),
+                &attrs,
+                Shaping::Advanced,
+                Some(self.text_align),
+            );
+        }
         // Perform shaping as desired
         buffer_borrowed.shape_until_scroll(true);
         // Get the size of the text block in pixels
@@ -243,6 +293,7 @@ impl Label {
             area_width: self.area_width.map(|i| i.to_bits()),
         };
         self.text.hash(&mut hasher);
+        self.spans.hash(&mut hasher);
         hashing_key.hash(&mut hasher);
         self.text_align.to_string().hash(&mut hasher);
         hasher.finish()
@@ -256,4 +307,17 @@ impl crate::component::Component for Label {
     fn touch(&mut self, _touch: &macroquad::prelude::Touch) -> anyhow::Result<bool> {
         Ok(false)
     }
+
+    fn resize(&mut self, _metrics: &ScreenMetrics) {
+        // The buffer is laid out in physical pixels
+        self.cached_buffer = None;
+    }
+
+    fn is_dirty(&mut self) -> bool {
+        self.rendered != Some((self.color, self.texture_align))
+            || self
+                .cached_buffer
+                .as_ref()
+                .is_none_or(|(hash, _, _)| *hash != self.state_hash())
+    }
 }
@@ -1,5 +1,7 @@
 pub mod button;
+pub mod icon;
 pub mod label;
+pub mod stats;
 
 pub trait DataComponent<D> {
     fn set_data(&mut self, data: D);
@@ -0,0 +1,166 @@
+use std::{collections::VecDeque, fmt::Write, time::Duration};
+
+use macroquad::color::Color;
+
+use crate::{
+    component::Component,
+    components::label::Label,
+    shading::IntoShading,
+    utils::{Point, Transform},
+    window::Window,
+};
+
+/// The [`FrameStats`](crate::stats::FrameStats) of the last frame and a graph of recent
+/// frame times, for watching performance on devices without a profiler attached.
+///
+/// Like [`Label`], the panel ignores the scale of its transform: its top-left corner is at
+/// the origin of its local coordinate system and it is [`Self::WIDTH`] logical pixels wide.
+/// Render it last so that it stays on top.
+pub struct StatsOverlay {
+    pub background: Color,
+    /// Frame time reaching the top of the graph
+    pub graph_max: Duration,
+    /// Frame times of the last [`Self::HISTORY`] frames, oldest first
+    history: VecDeque<Duration>,
+    last_frame: u64,
+    label: Label,
+}
+
+impl Default for StatsOverlay {
+    fn default() -> Self {
+        Self {
+            background: Color::new(0., 0., 0., 0.6),
+            graph_max: Duration::from_secs_f64(1. / 30.),
+            history: VecDeque::with_capacity(Self::HISTORY),
+            last_frame: 0,
+            label: Label::default()
+                .with_font_size(12.)
+                .with_line_height(15.)
+                .with_texture_align((0., 0.)),
+        }
+    }
+}
+
+impl StatsOverlay {
+    pub const WIDTH: f32 = 260.;
+    pub const HISTORY: usize = 120;
+    const PADDING: f32 = 6.;
+    const LINES: usize = 5;
+    const GRAPH_HEIGHT: f32 = 40.;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Height of the panel in logical pixels.
+    pub fn height(&self) -> f32 {
+        Self::PADDING * 3. + Self::LINES as f32 * self.label.line_height + Self::GRAPH_HEIGHT
+    }
+
+    fn fill_rect(target: &mut Window, x: f32, y: f32, w: f32, h: f32, color: Color) {
+        target.fill_quad(
+            [
+                Point::new(x, y),
+                Point::new(x + w, y),
+                Point::new(x + w, y + h),
+                Point::new(x, y + h),
+            ],
+            color.into_shading(),
+            1.,
+        );
+    }
+}
+
+impl Component for StatsOverlay {
+    fn touch(&mut self, _touch: &macroquad::prelude::Touch) -> anyhow::Result<bool> {
+        Ok(false)
+    }
+
+    fn render(&mut self, tr: &Transform, target: &mut Window) {
+        let stats = target.frame_stats().clone();
+        if stats.frame != self.last_frame {
+            self.last_frame = stats.frame;
+            if self.history.len() == Self::HISTORY {
+                self.history.pop_front();
+            }
+            self.history.push_back(stats.frame_time);
+        }
+
+        let origin = tr.transform_point(&Point::origin());
+        Self::fill_rect(
+            target,
+            origin.x,
+            origin.y,
+            Self::WIDTH,
+            self.height(),
+            self.background,
+        );
+
+        let ms = |d: Duration| d.as_secs_f32() * 1000.;
+        let text = &mut self.label.text;
+        text.clear();
+        let _ = writeln!(
+            text,
+            "{:.0} fps, {:.2} ms",
+            stats.fps(),
+            ms(stats.frame_time)
+        );
+        let _ = writeln!(
+            text,
+            "{} draw calls, {} vertices, {} indices",
+            stats.draw_calls, stats.vertices, stats.indices
+        );
+        let _ = writeln!(text, "tessellation {:.2} ms", ms(stats.tessellation_time));
+        let _ = writeln!(
+            text,
+            "glyphs {} hit, {} missed, {} evicted",
+            stats.glyph_hits, stats.glyph_misses, stats.glyph_evictions
+        );
+        let _ = write!(
+            text,
+            "atlas {:.1}% full, {} labels laid out",
+            stats.atlas_occupancy * 100.,
+            stats.labels_laid_out
+        );
+        self.label.render_text(
+            target,
+            Point::new(origin.x + Self::PADDING, origin.y + Self::PADDING),
+        );
+
+        let left = origin.x + Self::PADDING;
+        let bottom = origin.y + self.height() - Self::PADDING;
+        let bar_width = (Self::WIDTH - Self::PADDING * 2.) / Self::HISTORY as f32;
+        let max = self.graph_max.as_secs_f32();
+        // Recent frames on the right
+        let start = Self::HISTORY - self.history.len();
+        for (i, frame_time) in self.history.iter().enumerate() {
+            let seconds = frame_time.as_secs_f32();
+            let height = (seconds / max).min(1.) * Self::GRAPH_HEIGHT;
+            let color = if seconds <= 1. / 55. {
+                Color::new(0.3, 0.9, 0.4, 1.)
+            } else if seconds <= 1. / 28. {
+                Color::new(1., 0.8, 0.2, 1.)
+            } else {
+                Color::new(1., 0.3, 0.3, 1.)
+            };
+            Self::fill_rect(
+                target,
+                left + (start + i) as f32 * bar_width,
+                bottom - height,
+                bar_width,
+                height,
+                color,
+            );
+        }
+        // 60 fps
+        let target_height = (1. / 60. / max).min(1.) * Self::GRAPH_HEIGHT;
+        Self::fill_rect(
+            target,
+            left,
+            bottom - target_height,
+            Self::WIDTH - Self::PADDING * 2.,
+            1.,
+            Color::new(1., 1., 1., 0.5),
+        );
+    }
+}
@@ -0,0 +1,52 @@
+use macroquad::math::Rect;
+
+/// How [`Window::draw_nine_slice`](crate::window::Window::draw_nine_slice) cuts an image.
+///
+/// The insets (in texels) cut the image into a 3x3 grid: corners keep their size,
+/// edges stretch along the border and the center stretches both ways.
+#[derive(Clone, Debug)]
+pub struct NineSlice {
+    pub left: f32,
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+    /// Part of the texture holding the image, in texels. `None` is the whole texture.
+    pub source: Option<Rect>,
+    /// Size of a border texel in the coordinate system drawn in.
+    pub scale: f32,
+}
+
+impl Default for NineSlice {
+    /// No insets, a plain stretched image.
+    fn default() -> Self {
+        Self::new(0.0, 0.0, 0.0, 0.0)
+    }
+}
+
+impl NineSlice {
+    pub fn new(left: f32, top: f32, right: f32, bottom: f32) -> Self {
+        Self {
+            left,
+            top,
+            right,
+            bottom,
+            source: None,
+            scale: 1.0,
+        }
+    }
+
+    /// The same inset on all sides.
+    pub fn uniform(inset: f32) -> Self {
+        Self::new(inset, inset, inset, inset)
+    }
+
+    pub fn with_source(mut self, source: Rect) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    pub fn with_scale(mut self, scale: f32) -> Self {
+        self.scale = scale;
+        self
+    }
+}
@@ -0,0 +1,41 @@
+use crate::{batch::BlendMode, utils::Transform};
+
+/// How an offscreen layer is put back onto whatever it was drawn over.
+///
+/// Everything in a layer is drawn first and then composited as a whole, so overlapping
+/// children fade out together instead of showing through each other.
+#[derive(Clone, Debug)]
+pub struct LayerOptions {
+    /// Opacity of the whole layer.
+    pub alpha: f32,
+    /// Applied to the layer's content when compositing.
+    pub transform: Transform,
+    pub blend: BlendMode,
+}
+
+impl Default for LayerOptions {
+    fn default() -> Self {
+        Self {
+            alpha: 1.0,
+            transform: Transform::identity(),
+            blend: BlendMode::Normal,
+        }
+    }
+}
+
+impl LayerOptions {
+    pub fn with_alpha(mut self, alpha: f32) -> Self {
+        self.alpha = alpha;
+        self
+    }
+
+    pub fn with_transform(mut self, transform: Transform) -> Self {
+        self.transform = transform;
+        self
+    }
+
+    pub fn with_blend(mut self, blend: BlendMode) -> Self {
+        self.blend = blend;
+        self
+    }
+}
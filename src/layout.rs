@@ -1,8 +1,10 @@
-use macroquad::prelude::Touch;
+use macroquad::prelude::{Touch, TouchPhase};
 
+use crate::clip::Clip;
 use crate::component::Component;
-use crate::utils::Transform;
-use crate::window::Window;
+use crate::layer::LayerOptions;
+use crate::utils::{Point, Transform};
+use crate::window::{ScreenMetrics, Window};
 
 #[cfg(feature = "layout-debug")]
 const LAYOUT_DEBUG: bool = option_env!("COMUI_LAYOUT_DEBUG").is_some();
@@ -61,73 +63,119 @@ impl<'a> LayoutBuilder<'a> {
 pub trait Layout {
     fn components(&mut self) -> Vec<(Transform, &mut dyn Component)>;
 
+    /// Children are clipped to this region, in the coordinate system of the layout.
+    ///
+    /// Touches starting outside of it are not passed to children.
+    fn clip(&self) -> Option<Clip> {
+        None
+    }
+
+    /// Renders the layout, children included, into an offscreen layer composited with
+    /// these options. The transform is in the coordinate system of the layout.
+    fn layer(&self) -> Option<LayerOptions> {
+        None
+    }
+
     #[allow(unused_variables)]
     /// Called before rendering children.
     fn before_render(&mut self, tr: &Transform, target: &mut Window) {}
     #[allow(unused_variables)]
     /// Called after rendering children.
     fn after_render(&mut self, tr: &Transform, target: &mut Window) {}
+    #[allow(unused_variables)]
+    /// Called before children are told about new screen metrics.
+    fn on_resize(&mut self, metrics: &ScreenMetrics) {}
+    /// Whether rendering again would differ from the last render, children aside: what
+    /// [`Self::before_render`] and [`Self::after_render`] draw, the clip, the layer, or
+    /// which children [`Self::components`] returns and where. Children report their own
+    /// state through [`Component::is_dirty`].
+    ///
+    /// True by default, so a [`Retained`](crate::retained::Retained) subtree holding the
+    /// layout renders every frame. Layouts tracking their state should override it.
+    fn is_layout_dirty(&mut self) -> bool {
+        true
+    }
 }
 
 impl<T: Layout> Component for T {
     fn render(&mut self, tr: &Transform, target: &mut Window) {
+        let layer = self.layer().map(|mut layer| {
+            if let Some(inv_tr) = tr.try_inverse() {
+                layer.transform = tr * layer.transform * inv_tr;
+            }
+            layer
+        });
+        if layer.is_some() {
+            target.push_layer();
+        }
         self.before_render(tr, target);
+        let clip = self.clip();
+        if let Some(clip) = &clip {
+            target.push_clip(clip, tr);
+        }
         for (child_tr, child) in self.components() {
             let tr = tr * child_tr;
             child.render(&tr, target);
             #[cfg(feature = "layout-debug")]
-            {
-                // TODO: simplify this
-                use lyon::{
-                    geom::traits::Transformation,
-                    math::Box2D,
-                    path::{Builder, Winding},
-                };
-
+            if LAYOUT_DEBUG {
                 use crate::shading::IntoShading;
-                let mut builder = Builder::new();
-                builder.add_rectangle(
-                    &Box2D::new((-0.5, -0.5).into(), (0.5, 0.5).into()),
-                    Winding::Positive,
+                // In the final coordinate system, for a one pixel outline
+                let corners = [(-0.5, -0.5), (0.5, -0.5), (0.5, 0.5), (-0.5, 0.5)].map(|(x, y)| {
+                    let p = tr.transform_point(&Point::new(x, y));
+                    lyon::math::point(p.x, p.y)
+                });
+                let mut builder = lyon::path::Path::builder();
+                builder.add_polygon(lyon::path::Polygon {
+                    points: &corners,
+                    closed: true,
+                });
+                // Not cached, as it moves with the layout
+                target.stroke_path(
+                    &builder.build(),
+                    macroquad::color::RED.into_shading(),
+                    1.0,
+                    1.0,
                 );
-
-                struct MyTransform(Transform);
-
-                impl Transformation<f32> for MyTransform {
-                    fn transform_point(&self, p: lyon::geom::Point<f32>) -> lyon::geom::Point<f32> {
-                        let point = nalgebra::Point2::new(p.x, p.y);
-                        let new_point = self.0.transform_point(&point);
-                        lyon::geom::Point::new(new_point.x, new_point.y)
-                    }
-
-                    fn transform_vector(
-                        &self,
-                        v: lyon::geom::Vector<f32>,
-                    ) -> lyon::geom::Vector<f32> {
-                        let vector = nalgebra::Vector2::new(v.x, v.y);
-                        let new_vector = self.0.transform_vector(&vector);
-                        lyon::geom::Vector::new(new_vector.x, new_vector.y)
-                    }
-                }
-                if LAYOUT_DEBUG {
-                    target.stroke_path(
-                        &builder.build().transformed(&MyTransform(tr)),
-                        macroquad::color::RED.into_shading(),
-                        1.0,
-                        1.0,
-                    );
-                }
             }
         }
+        if clip.is_some() {
+            target.pop_clip();
+        }
         self.after_render(tr, target);
+        if let Some(layer) = &layer {
+            target.pop_layer(layer);
+        }
+    }
+
+    fn resize(&mut self, metrics: &ScreenMetrics) {
+        self.on_resize(metrics);
+        for (_, child) in self.components() {
+            child.resize(metrics);
+        }
+    }
+
+    fn is_dirty(&mut self) -> bool {
+        self.is_layout_dirty()
+            || self
+                .components()
+                .into_iter()
+                .any(|(_, child)| child.is_dirty())
     }
 
     fn touch(&mut self, touch: &Touch) -> anyhow::Result<bool> {
+        // Later phases still go through, so that children can see their touches end
+        if touch.phase == TouchPhase::Started
+            && self
+                .clip()
+                .is_some_and(|clip| !clip.contains(&Point::new(touch.position.x, touch.position.y)))
+        {
+            return Ok(false);
+        }
         for (child_tr, child) in self.components() {
-            if let Some(inv_tr) = child_tr.try_inverse() {
-                if child.touch(&transform_touch(touch, &inv_tr))? {
-                    return Ok(true);
-                }
+            if let Some(inv_tr) = child_tr.try_inverse()
+                && child.touch(&transform_touch(touch, &inv_tr))?
+            {
+                return Ok(true);
             }
         }
         Ok(false)
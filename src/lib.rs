@@ -1,9 +1,24 @@
+pub mod backdrop;
+pub mod backend;
+pub mod batch;
+pub mod clip;
+pub mod color;
 pub mod component;
 pub mod components;
+pub mod image;
 pub mod input;
+pub mod layer;
 pub mod layout;
+pub mod material;
+pub mod retained;
+pub mod rich_text;
 pub mod scene;
 pub mod shading;
+pub mod shadow;
+pub mod stats;
+pub mod stroke;
+pub mod svg;
+pub mod tessellation;
 pub mod text;
 pub mod utils;
 pub mod window;
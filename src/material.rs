@@ -0,0 +1,212 @@
+//! Custom fragment shaders, see [`MaterialShading`].
+
+use macroquad::{
+    color::Color,
+    material::{Material, MaterialParams},
+    math::{Mat4, Rect, Vec2, Vec3, Vec4, vec2},
+    miniquad::{
+        BlendFactor, BlendState, BlendValue, Equation, PipelineParams, ShaderSource, UniformDesc,
+        UniformType,
+    },
+    texture::Texture2D,
+    ui::Vertex,
+};
+use nalgebra::Vector2;
+
+use crate::{
+    backend::DEFAULT_VERTEX_SHADER,
+    shading::Shading,
+    utils::{Point, Transform},
+};
+
+/// Seconds since the app started, a `float`. Set by the backend.
+pub const TIME: &str = "Time";
+/// Size of the target in physical pixels, a `vec2`. Set by the backend.
+pub const RESOLUTION: &str = "Resolution";
+/// Size of the shaded rectangle in logical pixels, a `vec2`. Set by [`MaterialShading`].
+pub const LOCAL_SIZE: &str = "LocalSize";
+
+/// The uniforms every material drawn with [`MaterialShading`] must declare.
+pub fn builtin_uniforms() -> Vec<UniformDesc> {
+    vec![
+        UniformDesc::new(TIME, UniformType::Float1),
+        UniformDesc::new(RESOLUTION, UniformType::Float2),
+        UniformDesc::new(LOCAL_SIZE, UniformType::Float2),
+    ]
+}
+
+/// Compiles `fragment` into a material for [`MaterialShading`], with the built-in
+/// uniforms declared in addition to `uniforms`.
+///
+/// The fragment shader gets `varying lowp vec2 uv` and `varying lowp vec4 color`
+/// and must output premultiplied colors, like comui's own shaders.
+pub fn load_material(fragment: &str, uniforms: Vec<UniformDesc>) -> anyhow::Result<Material> {
+    let one_minus_source_alpha = BlendFactor::OneMinusValue(BlendValue::SourceAlpha);
+    let blend = Some(BlendState::new(
+        Equation::Add,
+        BlendFactor::One,
+        one_minus_source_alpha,
+    ));
+    let material = macroquad::material::load_material(
+        ShaderSource::Glsl {
+            vertex: DEFAULT_VERTEX_SHADER,
+            fragment,
+        },
+        MaterialParams {
+            pipeline_params: PipelineParams {
+                color_blend: blend,
+                alpha_blend: blend,
+                ..Default::default()
+            },
+            uniforms: builtin_uniforms().into_iter().chain(uniforms).collect(),
+            ..Default::default()
+        },
+    )?;
+    Ok(material)
+}
+
+/// Value of a uniform, of the matching GLSL type.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Uniform {
+    Float(f32),
+    Vec2(Vec2),
+    Vec3(Vec3),
+    Vec4(Vec4),
+    Int(i32),
+    Mat4(Mat4),
+}
+
+impl Uniform {
+    pub(crate) fn apply(&self, material: &Material, name: &str) {
+        match *self {
+            Uniform::Float(value) => material.set_uniform(name, value),
+            Uniform::Vec2(value) => material.set_uniform(name, value),
+            Uniform::Vec3(value) => material.set_uniform(name, value),
+            Uniform::Vec4(value) => material.set_uniform(name, value),
+            Uniform::Int(value) => material.set_uniform(name, value),
+            Uniform::Mat4(value) => material.set_uniform(name, value),
+        }
+    }
+}
+
+impl From<f32> for Uniform {
+    fn from(value: f32) -> Self {
+        Uniform::Float(value)
+    }
+}
+
+impl From<Vec2> for Uniform {
+    fn from(value: Vec2) -> Self {
+        Uniform::Vec2(value)
+    }
+}
+
+impl From<Vec3> for Uniform {
+    fn from(value: Vec3) -> Self {
+        Uniform::Vec3(value)
+    }
+}
+
+impl From<Vec4> for Uniform {
+    fn from(value: Vec4) -> Self {
+        Uniform::Vec4(value)
+    }
+}
+
+impl From<i32> for Uniform {
+    fn from(value: i32) -> Self {
+        Uniform::Int(value)
+    }
+}
+
+impl From<Mat4> for Uniform {
+    fn from(value: Mat4) -> Self {
+        Uniform::Mat4(value)
+    }
+}
+
+/// A `vec4` of the color components.
+impl From<Color> for Uniform {
+    fn from(color: Color) -> Self {
+        Uniform::Vec4(color.to_vec())
+    }
+}
+
+/// A material and the uniforms to draw with it, see [`DrawState`](crate::batch::DrawState).
+#[derive(Clone, Debug, PartialEq)]
+pub struct MaterialState {
+    pub material: Material,
+    /// Set before drawing, besides [`TIME`] and [`RESOLUTION`]
+    pub uniforms: Vec<(String, Uniform)>,
+}
+
+/// Shades with a custom fragment shader.
+///
+/// `uv` goes from (0, 0) at the top-left corner of the shaded rectangle to (1, 1) at the
+/// opposite one, whatever is actually drawn. The material decides how colors are blended,
+/// [`load_material`] makes one that blends like [`BlendMode::Normal`](crate::batch::BlendMode),
+/// and is only used by backends that have shaders, others draw the vertex color.
+///
+/// Materials can't be combined with other blend modes or drawn within stencil clips, the
+/// macroquad backend warns and draws the vertex color instead.
+#[derive(Clone, Debug)]
+pub struct MaterialShading {
+    state: MaterialState,
+    /// Final coordinate system -> uv
+    to_uv: Transform,
+    color: Color,
+}
+
+impl MaterialShading {
+    /// `rect` is the area `uv` spans, in the coordinate system of `tr`.
+    pub fn new(material: Material, tr: &Transform, rect: Rect) -> Self {
+        let to_rect = tr
+            * Transform::new_translation(&Vector2::new(rect.x, rect.y))
+            * Transform::new_nonuniform_scaling(&Vector2::new(rect.w, rect.h));
+        let local_size = vec2(
+            tr.transform_vector(&Vector2::new(rect.w, 0.)).norm(),
+            tr.transform_vector(&Vector2::new(0., rect.h)).norm(),
+        );
+        Self {
+            state: MaterialState {
+                material,
+                uniforms: vec![(LOCAL_SIZE.to_owned(), local_size.into())],
+            },
+            to_uv: to_rect.try_inverse().unwrap_or_else(Transform::identity),
+            color: Color::new(1., 1., 1., 1.),
+        }
+    }
+
+    pub fn with_uniform(mut self, name: impl Into<String>, value: impl Into<Uniform>) -> Self {
+        let name = name.into();
+        let value = value.into();
+        match self.state.uniforms.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, v)) => *v = value,
+            None => self.state.uniforms.push((name, value)),
+        }
+        self
+    }
+
+    /// The vertex color, white by default.
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+}
+
+impl Shading for MaterialShading {
+    fn new_vertex(&self, p: &Point, alpha: f32) -> Vertex {
+        let uv = self.to_uv.transform_point(p);
+        let mut color = self.color;
+        color.a *= alpha;
+        Vertex::new(p.x, p.y, 0., uv.x, uv.y, color)
+    }
+
+    fn texture(&self) -> Option<Texture2D> {
+        None
+    }
+
+    fn material(&self) -> Option<MaterialState> {
+        Some(self.state.clone())
+    }
+}
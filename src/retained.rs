@@ -0,0 +1,102 @@
+//! Retained rendering: subtrees that did not change replay what they drew last time,
+//! see [`Retained`].
+
+use macroquad::ui::Vertex;
+
+use crate::{
+    batch::DrawState,
+    clip::Clip,
+    component::Component,
+    layer::LayerOptions,
+    utils::Transform,
+    window::{ScreenMetrics, Window},
+};
+
+#[derive(Clone, Debug)]
+pub(crate) enum DisplayCommand {
+    Draw {
+        state: DrawState,
+        vertices: Vec<Vertex>,
+        indices: Vec<u16>,
+    },
+    PushClip(Clip, Transform),
+    PopClip,
+    PushLayer,
+    PopLayer(LayerOptions),
+}
+
+/// Everything drawn between [`Window::begin_recording`] and [`Window::end_recording`],
+/// in the final coordinate system.
+#[derive(Clone, Debug, Default)]
+pub struct DisplayList {
+    pub(crate) commands: Vec<DisplayCommand>,
+    /// Glyph atlas evictions when recording ended, evicted glyphs can't be replayed
+    pub(crate) atlas_evictions: u64,
+    /// Cleared by drawing that depends on what is below it, such as backdrop blurs
+    pub(crate) replayable: bool,
+}
+
+impl DisplayList {
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+}
+
+/// Renders `inner` once and replays the result for as long as neither it nor its transform
+/// changes, skipping layout, tessellation and text shaping.
+///
+/// Changes are detected with [`Component::is_dirty`], which is always true unless the
+/// component tracks its own state, and [`Self::mark_dirty`] for anything else. Backdrop
+/// blurs, shadows drawn with [`Window::draw_shadow`] and component captures depend on more
+/// than the subtree, so subtrees drawing them render every frame.
+pub struct Retained<C> {
+    pub inner: C,
+    dirty: bool,
+    cache: Option<(Transform, DisplayList)>,
+}
+
+impl<C: Component> Retained<C> {
+    pub fn new(inner: C) -> Self {
+        Self {
+            inner,
+            dirty: true,
+            cache: None,
+        }
+    }
+
+    /// Renders `inner` again next time, for changes it can't tell by itself.
+    pub fn mark_dirty(&mut self) {
+        self.dirty = true;
+    }
+}
+
+impl<C: Component> Component for Retained<C> {
+    fn touch(&mut self, touch: &macroquad::prelude::Touch) -> anyhow::Result<bool> {
+        self.inner.touch(touch)
+    }
+
+    fn render(&mut self, tr: &Transform, target: &mut Window) {
+        if !self.dirty
+            && !self.inner.is_dirty()
+            && let Some((cached_tr, list)) = &self.cache
+            && cached_tr == tr
+            && target.replay(list)
+        {
+            return;
+        }
+        target.begin_recording();
+        self.inner.render(tr, target);
+        self.cache = target.end_recording().map(|list| (*tr, list));
+        self.dirty = false;
+    }
+
+    fn resize(&mut self, metrics: &ScreenMetrics) {
+        // Text is rasterized for the DPI scale
+        self.dirty = true;
+        self.inner.resize(metrics);
+    }
+
+    fn is_dirty(&mut self) -> bool {
+        self.dirty || self.cache.is_none() || self.inner.is_dirty()
+    }
+}
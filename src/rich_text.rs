@@ -0,0 +1,342 @@
+//! Text in several styles, see [`Span`] and [`parse_markup`].
+
+use std::hash::{Hash, Hasher};
+
+use anyhow::{Context, bail, ensure};
+use cosmic_text::{Attrs, Family, Metrics};
+use macroquad::color::Color;
+
+use crate::{color, utils::macroquad_color_to_cosmic_color};
+
+pub use cosmic_text::{FamilyOwned, Style, Weight};
+
+/// A run of text in one style, see [`Label::with_spans`](crate::components::label::Label::with_spans).
+///
+/// Attributes left to `None` are those of the label.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Span {
+    pub text: String,
+    /// Replaces the color of the label, multiplied by its alpha
+    pub color: Option<Color>,
+    pub weight: Option<Weight>,
+    pub style: Option<Style>,
+    /// In logical pixels, the line height scales along
+    pub font_size: Option<f32>,
+    pub family: Option<FamilyOwned>,
+}
+
+impl Span {
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = Some(color);
+        self
+    }
+
+    pub fn with_weight(mut self, weight: Weight) -> Self {
+        self.weight = Some(weight);
+        self
+    }
+
+    pub fn with_style(mut self, style: Style) -> Self {
+        self.style = Some(style);
+        self
+    }
+
+    pub fn with_font_size(mut self, font_size: f32) -> Self {
+        self.font_size = Some(font_size);
+        self
+    }
+
+    pub fn with_family(mut self, family: FamilyOwned) -> Self {
+        self.family = Some(family);
+        self
+    }
+
+    pub fn bold(self) -> Self {
+        self.with_weight(Weight::BOLD)
+    }
+
+    pub fn italic(self) -> Self {
+        self.with_style(Style::Italic)
+    }
+
+    fn same_style(&self, other: &Span) -> bool {
+        self.color == other.color
+            && self.weight == other.weight
+            && self.style == other.style
+            && self.font_size == other.font_size
+            && self.family == other.family
+    }
+
+    /// The attributes of the span in a label of `metrics`, in physical pixels.
+    pub(crate) fn attrs(&self, metrics: Metrics, dpi_scale: f32) -> Attrs<'_> {
+        let mut attrs = Attrs::new();
+        if let Some(color) = self.color {
+            attrs = attrs.color(macroquad_color_to_cosmic_color(color));
+        }
+        if let Some(weight) = self.weight {
+            attrs = attrs.weight(weight);
+        }
+        if let Some(style) = self.style {
+            attrs = attrs.style(style);
+        }
+        if let Some(font_size) = self.font_size {
+            attrs = attrs.metrics(Metrics::relative(
+                font_size * dpi_scale,
+                metrics.line_height / metrics.font_size,
+            ));
+        }
+        if let Some(family) = &self.family {
+            attrs = attrs.family(family.as_family());
+        }
+        attrs
+    }
+}
+
+impl Hash for Span {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.text.hash(state);
+        self.color
+            .map(|c| [c.r, c.g, c.b, c.a].map(f32::to_bits))
+            .hash(state);
+        self.weight.hash(state);
+        self.style.hash(state);
+        self.font_size.map(f32::to_bits).hash(state);
+        self.family.hash(state);
+    }
+}
+
+/// Parses text with style tags into spans:
+///
+/// - `<b>bold</b>`
+/// - `<i>italic</i>`
+/// - `<color=#ff0>yellow</color>`, any color [`color::parse`] accepts
+/// - `<size=24>larger</size>`, in logical pixels. Sizes that aren't positive numbers
+///   leave both tags in the text.
+/// - `<font=monospace>code</font>`, a font family name or `serif`, `sans-serif`,
+///   `monospace`, `cursive` or `fantasy`
+///
+/// Tags nest and must be closed in order, tags still open at the end apply to the rest
+/// of the text. `\<` and `\\` are a literal `<` and `\`, other backslashes are kept as
+/// they are.
+pub fn parse_markup(markup: &str) -> anyhow::Result<Vec<Span>> {
+    let mut spans = Vec::new();
+    let mut style = Span::default();
+    // Open tags and the style before each of them, `None` for tags kept as text
+    let mut open: Vec<(&str, Option<Span>)> = Vec::new();
+    let mut text = String::new();
+    let mut rest = markup;
+    while let Some(i) = rest.find(['<', '\\']) {
+        text.push_str(&rest[..i]);
+        rest = &rest[i..];
+        if let Some(escaped) = rest.strip_prefix('\\') {
+            match escaped.strip_prefix(['<', '\\']) {
+                Some(after) => {
+                    text.push_str(&escaped[..1]);
+                    rest = after;
+                }
+                None => {
+                    text.push('\\');
+                    rest = escaped;
+                }
+            }
+            continue;
+        }
+        let end = rest
+            .find('>')
+            .with_context(|| format!("unterminated tag {rest:?}"))?;
+        let raw = &rest[..=end];
+        let tag = &rest[1..end];
+        rest = &rest[end + 1..];
+        if let Some(name) = tag.strip_prefix('/') {
+            let name = name.trim();
+            let (expected, previous) = open
+                .pop()
+                .with_context(|| format!("</{name}> closes no tag"))?;
+            ensure!(name == expected, "expected </{expected}>, found </{name}>");
+            match previous {
+                Some(previous) => {
+                    push_span(&mut spans, &style, std::mem::take(&mut text));
+                    style = previous;
+                }
+                None => text.push_str(raw),
+            }
+            continue;
+        }
+        let (name, value) = match tag.split_once('=') {
+            Some((name, value)) => (name.trim(), Some(value.trim())),
+            None => (tag.trim(), None),
+        };
+        let mut new = style.clone();
+        match (name, value) {
+            ("b", None) => new.weight = Some(Weight::BOLD),
+            ("i", None) => new.style = Some(Style::Italic),
+            ("color", Some(value)) => new.color = Some(color::parse(value)?),
+            ("size", Some(value)) => match value.parse::<f32>() {
+                Ok(size) if size.is_finite() && size > 0. => new.font_size = Some(size),
+                _ => {
+                    // Kept as text, along with its closing tag
+                    text.push_str(raw);
+                    open.push((name, None));
+                    continue;
+                }
+            },
+            ("font", Some(value)) => new.family = Some(family(value)),
+            _ => bail!("unknown tag <{tag}>"),
+        }
+        push_span(&mut spans, &style, std::mem::take(&mut text));
+        open.push((name, Some(std::mem::replace(&mut style, new))));
+    }
+    text.push_str(rest);
+    push_span(&mut spans, &style, text);
+    Ok(spans)
+}
+
+/// Appends `text` in `style`, merging it with the last span if it has the same style.
+fn push_span(spans: &mut Vec<Span>, style: &Span, text: String) {
+    if text.is_empty() {
+        return;
+    }
+    match spans.last_mut() {
+        Some(last) if last.same_style(style) => last.text.push_str(&text),
+        _ => spans.push(Span {
+            text,
+            ..style.clone()
+        }),
+    }
+}
+
+fn family(name: &str) -> FamilyOwned {
+    match name {
+        "serif" => FamilyOwned::Serif,
+        "sans-serif" => FamilyOwned::SansSerif,
+        "monospace" => FamilyOwned::Monospace,
+        "cursive" => FamilyOwned::Cursive,
+        "fantasy" => FamilyOwned::Fantasy,
+        name => FamilyOwned::new(Family::Name(name)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(markup: &str) -> String {
+        format!("{:#}", parse_markup(markup).unwrap_err())
+    }
+
+    #[test]
+    fn plain_text() {
+        assert_eq!(parse_markup("plain").unwrap(), vec![Span::new("plain")]);
+        assert!(parse_markup("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn nested_tags() {
+        let red = Color::new(1., 0., 0., 1.);
+        assert_eq!(
+            parse_markup("a<b>b<color=#f00>c<i>d</i></color></b><size=24>e</size>").unwrap(),
+            vec![
+                Span::new("a"),
+                Span::new("b").bold(),
+                Span::new("c").bold().with_color(red),
+                Span::new("d").bold().with_color(red).italic(),
+                Span::new("e").with_font_size(24.),
+            ]
+        );
+    }
+
+    #[test]
+    fn fonts() {
+        assert_eq!(
+            parse_markup("<font=monospace>a</font><font= Fira Mono >b</font>").unwrap(),
+            vec![
+                Span::new("a").with_family(FamilyOwned::Monospace),
+                Span::new("b").with_family(FamilyOwned::Name("Fira Mono".into())),
+            ]
+        );
+    }
+
+    #[test]
+    fn merges_spans_of_the_same_style() {
+        assert_eq!(
+            parse_markup("<b>a</b><b>b</b>c<i></i>d").unwrap(),
+            vec![Span::new("ab").bold(), Span::new("cd")]
+        );
+    }
+
+    #[test]
+    fn unclosed_tags_apply_to_the_rest() {
+        assert_eq!(
+            parse_markup("a<b>b<i>c").unwrap(),
+            vec![
+                Span::new("a"),
+                Span::new("b").bold(),
+                Span::new("c").bold().italic(),
+            ]
+        );
+    }
+
+    #[test]
+    fn mismatched_tags() {
+        assert_eq!(error("<b><i>a</b></i>"), "expected </i>, found </b>");
+        assert_eq!(error("a</b>"), "</b> closes no tag");
+        assert_eq!(error("a<b"), "unterminated tag \"<b\"");
+    }
+
+    #[test]
+    fn unknown_tags_and_attributes() {
+        assert_eq!(error("<u>a</u>"), "unknown tag <u>");
+        assert_eq!(error("<b=1>a</b>"), "unknown tag <b=1>");
+        assert_eq!(error("<color>a</color>"), "unknown tag <color>");
+        assert_eq!(error("<colour=red>a</colour>"), "unknown tag <colour=red>");
+        assert!(error("<color=reddish>a</color>").contains("invalid color"));
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(
+            parse_markup(r"\<b>not bold\</b> \\<b>bold</b>").unwrap(),
+            vec![Span::new(r"<b>not bold</b> \"), Span::new("bold").bold()]
+        );
+        // Other backslashes are literal
+        assert_eq!(
+            parse_markup(r"C:\path\n <b>\d</b>\").unwrap(),
+            vec![
+                Span::new(r"C:\path\n "),
+                Span::new(r"\d").bold(),
+                Span::new(r"\"),
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_invalid_sizes_as_text() {
+        for size in ["0", "-3", "NaN", "inf", "big", ""] {
+            let markup = format!("a<size={size}>b<b>c</b></size>d");
+            assert_eq!(
+                parse_markup(&markup).unwrap(),
+                vec![
+                    Span::new(format!("a<size={size}>b")),
+                    Span::new("c").bold(),
+                    Span::new("</size>d"),
+                ],
+                "{markup}"
+            );
+        }
+        assert_eq!(
+            error("<size=0><b>a</size></b>"),
+            "expected </b>, found </size>"
+        );
+        assert_eq!(
+            parse_markup("<size=0.5>a").unwrap(),
+            vec![Span::new("a").with_font_size(0.5)]
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{component::Component, layout::Layout, utils::Transform};
+use crate::{component::Component, layout::Layout, utils::Transform, window::ScreenMetrics};
 
 pub enum NextScene {
     Pop,
@@ -9,20 +9,32 @@ pub enum NextScene {
 
 pub trait Scene: Component {
     /// `None`: no change, `Some(NextScene)`: change scene
+    ///
+    /// Asked after each render, so a scene about to change must be dirty, see
+    /// [`Component::is_dirty`]. Replayed renders ask nothing.
     fn next_scene(&mut self) -> Option<NextScene>;
 }
 
 pub struct SceneManager {
+    /// Call [`Self::mark_changed`] after changing it by hand.
     pub scene_stack: Vec<Box<dyn Scene>>,
+    /// Whether the current scene changed since the last render
+    changed: bool,
 }
 
 impl SceneManager {
     pub fn new(base: impl Scene + 'static) -> Self {
         Self {
             scene_stack: vec![Box::new(base)],
+            changed: true,
         }
     }
 
+    /// Renders the current scene again, even if it is clean.
+    pub fn mark_changed(&mut self) {
+        self.changed = true;
+    }
+
     pub fn current_scene(&mut self) -> &mut Box<dyn Scene> {
         self.scene_stack
             .last_mut()
@@ -38,8 +50,21 @@ impl Layout for SceneManager {
         )]
     }
 
+    /// Only the current scene is a child, covered ones must be up to date once uncovered.
+    fn on_resize(&mut self, metrics: &ScreenMetrics) {
+        let covered = self.scene_stack.len().saturating_sub(1);
+        for scene in &mut self.scene_stack[..covered] {
+            scene.resize(metrics);
+        }
+    }
+
+    fn before_render(&mut self, _: &Transform, _: &mut crate::window::Window) {
+        self.changed = false;
+    }
+
     fn after_render(&mut self, _: &Transform, _: &mut crate::window::Window) {
         if let Some(next_scene) = self.current_scene().next_scene() {
+            self.changed = true;
             match next_scene {
                 NextScene::Pop => {
                     self.scene_stack.pop();
@@ -59,4 +84,9 @@ impl Layout for SceneManager {
             }
         }
     }
+
+    /// Draws nothing itself, so only a scene change makes it dirty.
+    fn is_layout_dirty(&mut self) -> bool {
+        self.changed
+    }
 }
@@ -1,15 +1,90 @@
 use lyon::tessellation::{
     FillVertex, FillVertexConstructor, StrokeVertex, StrokeVertexConstructor,
 };
-use macroquad::{texture::Texture2D, ui::Vertex};
+use std::{
+    f32::consts::TAU,
+    hash::{Hash, Hasher},
+};
+
+use macroquad::{
+    color::Color,
+    math::{Rect, Vec2, vec2},
+    texture::Texture2D,
+    ui::Vertex,
+};
+use nalgebra::{Rotation2, Vector2};
 
-use crate::utils::Point;
+pub use crate::batch::BlendMode;
+use crate::{
+    color::{ColorExt, ColorSpace},
+    material::MaterialState,
+    utils::{Point, Transform},
+};
 
 pub trait Shading {
     // TODO: why we need alpha here???
     /// `p`: (x, y) in final coordinate system
     fn new_vertex(&self, p: &Point, alpha: f32) -> Vertex;
     fn texture(&self) -> Option<Texture2D>;
+    /// How the shaded geometry is blended with what is already drawn.
+    fn blend_mode(&self) -> BlendMode {
+        BlendMode::Normal
+    }
+    /// A custom shader to draw with instead of the default one.
+    fn material(&self) -> Option<MaterialState> {
+        None
+    }
+    /// A gradient colored per pixel, its coordinates being the texture coordinates.
+    fn gradient(&self) -> Option<Gradient> {
+        None
+    }
+    /// What the texture shows outside of texture coordinates 0..=1.
+    fn wrap(&self) -> Wrap {
+        Wrap::Clamp
+    }
+
+    /// The same shading, drawn with `blend` instead.
+    fn with_blend(self, blend: BlendMode) -> Blended<Self>
+    where
+        Self: Sized,
+    {
+        Blended {
+            shading: self,
+            blend,
+        }
+    }
+}
+
+/// See [`Shading::with_blend`].
+pub struct Blended<S> {
+    shading: S,
+    blend: BlendMode,
+}
+
+impl<S: Shading> Shading for Blended<S> {
+    fn new_vertex(&self, p: &Point, alpha: f32) -> Vertex {
+        self.shading.new_vertex(p, alpha)
+    }
+
+    fn texture(&self) -> Option<Texture2D> {
+        self.shading.texture()
+    }
+
+    fn blend_mode(&self) -> BlendMode {
+        self.blend
+    }
+
+    fn material(&self) -> Option<MaterialState> {
+        self.shading.material()
+    }
+
+    fn gradient(&self) -> Option<Gradient> {
+        self.shading.gradient()
+    }
+
+    fn wrap(&self) -> Wrap {
+        self.shading.wrap()
+    }
 }
 pub trait IntoShading {
     type Target: Shading;
@@ -36,44 +111,612 @@ impl<T: Shading> StrokeVertexConstructor<Vertex> for ShadedConstructor<T> {
     }
 }
 
-pub struct GradientShading {
-    origin: (f32, f32),
-    color: macroquad::color::Color,
-    vector: (f32, f32),
-    color_end: macroquad::color::Color,
+/// Maps a premultiplied texture covering the screen, such as a layer, onto what is drawn.
+pub(crate) struct ScreenTextureShading {
+    pub texture: Texture2D,
+    /// In logical pixels
+    pub screen_size: (f32, f32),
 }
 
-impl Shading for GradientShading {
+impl Shading for ScreenTextureShading {
     fn new_vertex(&self, p: &Point, alpha: f32) -> Vertex {
-        let mut color = {
-            let (dx, dy) = (p.x - self.origin.0, p.y - self.origin.1);
-            let t = dx * self.vector.0 + dy * self.vector.1;
-            macroquad::color::Color {
-                r: self.color.r * (1.0 - t) + self.color_end.r * t,
-                g: self.color.g * (1.0 - t) + self.color_end.g * t,
-                b: self.color.b * (1.0 - t) + self.color_end.b * t,
-                a: self.color.a * (1.0 - t) + self.color_end.a * t,
+        let (width, height) = self.screen_size;
+        Vertex::new(
+            p.x,
+            p.y,
+            0.,
+            p.x / width,
+            p.y / height,
+            Color::new(alpha, alpha, alpha, alpha),
+        )
+    }
+
+    fn texture(&self) -> Option<Texture2D> {
+        Some(self.texture.clone())
+    }
+
+    fn blend_mode(&self) -> BlendMode {
+        BlendMode::Premultiplied
+    }
+}
+
+/// What happens to a gradient outside of its first and last stop.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Spread {
+    /// The end colors extend
+    #[default]
+    Pad,
+    Repeat,
+    Reflect,
+}
+
+impl Spread {
+    /// Maps any offset into 0..=1.
+    pub fn apply(self, t: f32) -> f32 {
+        match self {
+            Spread::Pad => t.clamp(0., 1.),
+            Spread::Repeat => t.rem_euclid(1.),
+            Spread::Reflect => 1. - (t.rem_euclid(2.) - 1.).abs(),
+        }
+    }
+}
+
+/// The shape of a gradient in its normalized space, see [`Gradient::offset`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GradientKind {
+    /// The offset is x
+    Linear,
+    /// Circles growing from `focal` at offset 0 to the unit circle at offset 1
+    Radial { focal: Vec2 },
+    /// The offset is the angle from the positive x axis towards the positive y axis,
+    /// a full turn being 1
+    Conic,
+}
+
+/// How a backend colors a gradient, from texture coordinates in its normalized space.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Gradient {
+    pub kind: GradientKind,
+    /// `(offset, color)`, offsets increasing from 0 to 1. Colors are interpolated
+    /// without premultiplying them, like in SVG.
+    pub stops: Vec<(f32, Color)>,
+    pub spread: Spread,
+    /// Where colors are interpolated between stops
+    pub space: ColorSpace,
+}
+
+impl Gradient {
+    /// The offset at `p` in normalized space, before spreading. `None` where a radial
+    /// gradient with its focal point outside the circle is not defined.
+    pub fn offset(&self, p: Vec2) -> Option<f32> {
+        match self.kind {
+            GradientKind::Linear => Some(p.x),
+            GradientKind::Radial { focal } => {
+                // Solving |d - t e| = t for the circle through `p`
+                let (d, e) = (p - focal, -focal);
+                let (de, dd) = (d.dot(e), d.length_squared());
+                let a = e.length_squared() - 1.;
+                let t = if a.abs() < 1e-5 {
+                    dd / (2. * de)
+                } else {
+                    let root = (de * de - a * dd).sqrt();
+                    ((de - root) / a).max((de + root) / a)
+                };
+                (t >= 0.).then_some(t)
             }
+            GradientKind::Conic => {
+                // Tiny negative angles round to a full turn, which is the start again
+                let t = (p.y.atan2(p.x) / TAU).rem_euclid(1.);
+                Some(if t < 1. { t } else { 0. })
+            }
+        }
+    }
+
+    /// The color at `t`, in 0..=1.
+    pub fn color(&self, t: f32) -> Color {
+        let Some(&(first_offset, first)) = self.stops.first() else {
+            return Color::new(0., 0., 0., 0.);
         };
-        color.a *= alpha;
+        if t <= first_offset {
+            return first;
+        }
+        for pair in self.stops.windows(2) {
+            let [(from_offset, from), (to_offset, to)] = [pair[0], pair[1]];
+            if t <= to_offset {
+                let s = if to_offset > from_offset {
+                    (t - from_offset) / (to_offset - from_offset)
+                } else {
+                    1.
+                };
+                return from.mix(to, s, self.space);
+            }
+        }
+        self.stops[self.stops.len() - 1].1
+    }
+
+    /// The color at `p` in normalized space, transparent where it is not defined.
+    pub fn color_at(&self, p: Vec2) -> Color {
+        match self.offset(p) {
+            Some(t) if t.is_finite() => self.color(self.spread.apply(t)),
+            _ => Color::new(0., 0., 0., 0.),
+        }
+    }
+
+    /// Identifies the stops and how they are interpolated, for caching lookup tables of them.
+    pub fn stops_hash(&self) -> u64 {
+        let mut hasher = std::hash::DefaultHasher::new();
+        self.space.hash(&mut hasher);
+        for (offset, color) in &self.stops {
+            [*offset, color.r, color.g, color.b, color.a]
+                .map(f32::to_bits)
+                .hash(&mut hasher);
+        }
+        hasher.finish()
+    }
+}
+
+/// A plain color or a linear, radial or conic gradient with any number of stops.
+///
+/// Gradients are colored per pixel, their points are in the coordinate system
+/// of [`Self::with_transform`], the final one by default.
+#[derive(Clone, Debug)]
+pub struct GradientShading {
+    /// `None` for a plain color
+    gradient: Option<Gradient>,
+    color: Color,
+    /// Coordinate system of the points -> normalized space of the gradient
+    to_normalized: Transform,
+    /// Final coordinate system -> normalized space of the gradient
+    to_gradient: Transform,
+}
+
+impl GradientShading {
+    pub fn solid(color: Color) -> Self {
+        Self {
+            gradient: None,
+            color,
+            to_normalized: Transform::identity(),
+            to_gradient: Transform::identity(),
+        }
+    }
+
+    /// From `start` at offset 0 to `end` at offset 1.
+    pub fn linear(start: Point, end: Point, stops: impl Into<Vec<(f32, Color)>>) -> Self {
+        let d = end - start;
+        let from_normalized = Transform::new(d.x, -d.y, start.x, d.y, d.x, start.y, 0., 0., 1.);
+        Self::gradient(GradientKind::Linear, from_normalized, stops)
+    }
+
+    /// From `center` at offset 0 to the circle of `radius` at offset 1.
+    pub fn radial(center: Point, radius: f32, stops: impl Into<Vec<(f32, Color)>>) -> Self {
+        let from_normalized =
+            Transform::new_translation(&center.coords) * Transform::new_scaling(radius);
+        Self::gradient(
+            GradientKind::Radial { focal: Vec2::ZERO },
+            from_normalized,
+            stops,
+        )
+    }
+
+    /// Sweeping around `center`, starting at `start_angle` (radians, from the positive x axis
+    /// towards the positive y axis).
+    pub fn conic(center: Point, start_angle: f32, stops: impl Into<Vec<(f32, Color)>>) -> Self {
+        let from_normalized = Transform::new_translation(&center.coords)
+            * Rotation2::new(start_angle).to_homogeneous();
+        Self::gradient(GradientKind::Conic, from_normalized, stops)
+    }
+
+    fn gradient(
+        kind: GradientKind,
+        from_normalized: Transform,
+        stops: impl Into<Vec<(f32, Color)>>,
+    ) -> Self {
+        let mut stops = stops.into();
+        for (offset, _) in &mut stops {
+            *offset = offset.clamp(0., 1.);
+        }
+        stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+        // Degenerate gradients show their first stop
+        let to_normalized = from_normalized
+            .try_inverse()
+            .unwrap_or_else(|| Transform::new(0., 0., 0., 0., 0., 0., 0., 0., 1.));
+        let plain = stops.windows(2).all(|pair| pair[0].1 == pair[1].1);
+        Self {
+            color: stops.first().map_or(Color::new(0., 0., 0., 0.), |s| s.1),
+            gradient: (!plain).then_some(Gradient {
+                kind,
+                stops,
+                spread: Spread::Pad,
+                space: ColorSpace::Srgb,
+            }),
+            to_normalized,
+            to_gradient: to_normalized,
+        }
+    }
+
+    /// Where a radial gradient starts, in the same coordinate system as its center.
+    pub fn with_focal(mut self, focal: Point) -> Self {
+        if let Some(Gradient {
+            kind: GradientKind::Radial { focal: f },
+            ..
+        }) = &mut self.gradient
+        {
+            let p = self.to_normalized.transform_point(&focal);
+            *f = vec2(p.x, p.y);
+        }
+        self
+    }
+
+    pub fn with_spread(mut self, spread: Spread) -> Self {
+        if let Some(gradient) = &mut self.gradient {
+            gradient.spread = spread;
+        }
+        self
+    }
 
-        Vertex::new(p.x, p.y, 0., 0., 0., color)
+    /// Interpolates colors in `space` instead of sRGB.
+    pub fn with_color_space(mut self, space: ColorSpace) -> Self {
+        if let Some(gradient) = &mut self.gradient {
+            gradient.space = space;
+        }
+        self
+    }
+
+    /// `tr` maps the coordinate system the gradient is defined in to the final one.
+    pub fn with_transform(mut self, tr: &Transform) -> Self {
+        if let Some(inverse) = tr.try_inverse() {
+            self.to_gradient = self.to_normalized * inverse;
+        }
+        self
+    }
+}
+
+impl Shading for GradientShading {
+    fn new_vertex(&self, p: &Point, alpha: f32) -> Vertex {
+        match &self.gradient {
+            None => {
+                let mut color = self.color;
+                color.a *= alpha;
+                Vertex::new(p.x, p.y, 0., 0., 0., color)
+            }
+            Some(_) => {
+                let uv = self.to_gradient.transform_point(p);
+                Vertex::new(p.x, p.y, 0., uv.x, uv.y, Color::new(1., 1., 1., alpha))
+            }
+        }
     }
 
     fn texture(&self) -> Option<Texture2D> {
         None
     }
+
+    fn gradient(&self) -> Option<Gradient> {
+        self.gradient.clone()
+    }
+}
+
+/// What a texture shows outside of texture coordinates 0..=1, see [`Shading::wrap`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Wrap {
+    /// The edge pixels extend
+    #[default]
+    Clamp,
+    Repeat,
+    /// Nothing is drawn
+    Transparent,
+}
+
+/// Maps a texture onto what is drawn, such as an image filling a rounded rectangle
+/// or a pattern repeating over a path.
+///
+/// The texture is laid out over a rectangle in the coordinate system of the transform
+/// it is created with, then drawn wherever the shaded geometry is.
+#[derive(Clone, Debug)]
+pub struct TextureShading {
+    texture: Texture2D,
+    /// Final coordinate system -> texture coordinates
+    to_uv: Transform,
+    wrap: Wrap,
+    color: Color,
+}
+
+impl TextureShading {
+    /// Stretches `texture` over `rect`.
+    pub fn stretch(texture: Texture2D, tr: &Transform, rect: Rect) -> Self {
+        Self::new(texture, tr, rect, Wrap::Clamp)
+    }
+
+    /// Scales `texture` to cover `rect`, keeping its aspect ratio and cropping it evenly
+    /// on both sides.
+    ///
+    /// Only the aspect ratio of `texture_size` matters, `texture.size()` with macroquad.
+    pub fn cover(texture: Texture2D, texture_size: (f32, f32), tr: &Transform, rect: Rect) -> Self {
+        let scale = (rect.w / texture_size.0).max(rect.h / texture_size.1);
+        Self::new(texture, tr, fit(rect, texture_size, scale), Wrap::Clamp)
+    }
+
+    /// Scales `texture` to fit in `rect`, keeping its aspect ratio and centering it.
+    /// The rest of `rect` is transparent.
+    ///
+    /// Only the aspect ratio of `texture_size` matters, `texture.size()` with macroquad.
+    pub fn contain(
+        texture: Texture2D,
+        texture_size: (f32, f32),
+        tr: &Transform,
+        rect: Rect,
+    ) -> Self {
+        let scale = (rect.w / texture_size.0).min(rect.h / texture_size.1);
+        Self::new(
+            texture,
+            tr,
+            fit(rect, texture_size, scale),
+            Wrap::Transparent,
+        )
+    }
+
+    /// Repeats `texture` in every direction, `tile` being one copy of it.
+    pub fn tile(texture: Texture2D, tr: &Transform, tile: Rect) -> Self {
+        Self::new(texture, tr, tile, Wrap::Repeat)
+    }
+
+    fn new(texture: Texture2D, tr: &Transform, rect: Rect, wrap: Wrap) -> Self {
+        let to_rect = tr
+            * Transform::new_translation(&Vector2::new(rect.x, rect.y))
+            * Transform::new_nonuniform_scaling(&Vector2::new(rect.w, rect.h));
+        Self {
+            texture,
+            // Degenerate rectangles show a single texel
+            to_uv: to_rect
+                .try_inverse()
+                .unwrap_or_else(|| Transform::new(0., 0., 0., 0., 0., 0., 0., 0., 1.)),
+            wrap,
+            color: Color::new(1., 1., 1., 1.),
+        }
+    }
+
+    /// Transforms the texture coordinates once laid out, (0, 0) and (1, 1) being opposite
+    /// corners of the texture. For example, scaling them by 2 shows the texture at half
+    /// its size.
+    pub fn with_uv_transform(mut self, tr: &Transform) -> Self {
+        self.to_uv = tr * self.to_uv;
+        self
+    }
+
+    /// Multiplies the texture, white by default.
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+}
+
+/// `rect` shrunk or grown around its center to `texture_size` times `scale`.
+fn fit(rect: Rect, texture_size: (f32, f32), scale: f32) -> Rect {
+    let (w, h) = (texture_size.0 * scale, texture_size.1 * scale);
+    if !(w.is_finite() && h.is_finite()) {
+        return rect;
+    }
+    Rect::new(rect.x + (rect.w - w) / 2., rect.y + (rect.h - h) / 2., w, h)
+}
+
+impl Shading for TextureShading {
+    fn new_vertex(&self, p: &Point, alpha: f32) -> Vertex {
+        let uv = self.to_uv.transform_point(p);
+        let mut color = self.color;
+        color.a *= alpha;
+        Vertex::new(p.x, p.y, 0., uv.x, uv.y, color)
+    }
+
+    fn texture(&self) -> Option<Texture2D> {
+        Some(self.texture.clone())
+    }
+
+    fn wrap(&self) -> Wrap {
+        self.wrap
+    }
 }
 
 impl IntoShading for macroquad::color::Color {
     type Target = GradientShading;
 
     fn into_shading(self) -> Self::Target {
-        GradientShading {
-            origin: (0., 0.),
-            color: self,
-            vector: (0., 0.),
-            color_end: self,
+        GradientShading::solid(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use super::*;
+
+    const RED: Color = Color::new(1., 0., 0., 1.);
+    const GREEN: Color = Color::new(0., 1., 0., 1.);
+    const BLUE: Color = Color::new(0., 0., 1., 1.);
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!((actual - expected).abs() < 1e-4, "{actual} != {expected}");
+    }
+
+    fn radial(focal: Vec2) -> Gradient {
+        Gradient {
+            kind: GradientKind::Radial { focal },
+            stops: vec![(0., RED), (1., BLUE)],
+            spread: Spread::Pad,
+            space: ColorSpace::Srgb,
+        }
+    }
+
+    /// The offset of `shading` at `p` in the final coordinate system.
+    fn offset_at(shading: &GradientShading, p: Point) -> Option<f32> {
+        let uv = shading.new_vertex(&p, 1.).uv;
+        shading.gradient().unwrap().offset(uv)
+    }
+
+    #[test]
+    fn linear() {
+        let shading = GradientShading::linear(
+            Point::new(10., 10.),
+            Point::new(10., 30.),
+            [(0., RED), (1., BLUE)],
+        );
+        assert_close(offset_at(&shading, Point::new(10., 10.)).unwrap(), 0.);
+        assert_close(offset_at(&shading, Point::new(10., 30.)).unwrap(), 1.);
+        // Perpendicular to the gradient nothing changes
+        assert_close(offset_at(&shading, Point::new(-50., 15.)).unwrap(), 0.25);
+        assert_close(offset_at(&shading, Point::new(10., 0.)).unwrap(), -0.5);
+    }
+
+    #[test]
+    fn degenerate_linear_shows_the_first_stop() {
+        let p = Point::new(5., 5.);
+        let shading = GradientShading::linear(p, p, [(0.2, RED), (1., BLUE)]);
+        for q in [p, Point::new(100., -3.)] {
+            assert_eq!(shading.new_vertex(&q, 1.).uv, Vec2::ZERO);
+        }
+        assert_eq!(shading.gradient().unwrap().color_at(Vec2::ZERO), RED);
+    }
+
+    #[test]
+    fn radial_from_the_center() {
+        let shading = GradientShading::radial(Point::new(50., 50.), 20., [(0., RED), (1., BLUE)]);
+        assert_close(offset_at(&shading, Point::new(50., 50.)).unwrap(), 0.);
+        assert_close(offset_at(&shading, Point::new(60., 50.)).unwrap(), 0.5);
+        assert_close(offset_at(&shading, Point::new(50., 20.)).unwrap(), 1.5);
+    }
+
+    #[test]
+    fn radial_from_a_focal_point() {
+        let gradient = radial(vec2(0.5, 0.));
+        assert_close(gradient.offset(vec2(0.5, 0.)).unwrap(), 0.);
+        for p in [vec2(1., 0.), vec2(-1., 0.), vec2(0., 1.), vec2(0.6, -0.8)] {
+            assert_close(gradient.offset(p).unwrap(), 1.);
+        }
+        // Offsets grow linearly from the focal point to the circle
+        assert_close(gradient.offset(vec2(-0.25, 0.)).unwrap(), 0.5);
+        assert_close(gradient.offset(vec2(0.75, 0.)).unwrap(), 0.5);
+
+        let shading = GradientShading::radial(Point::new(50., 50.), 20., [(0., RED), (1., BLUE)])
+            .with_focal(Point::new(60., 50.));
+        assert_eq!(
+            shading.gradient().unwrap().kind,
+            GradientKind::Radial {
+                focal: vec2(0.5, 0.)
+            }
+        );
+    }
+
+    #[test]
+    fn radial_with_the_focal_point_on_the_circle() {
+        // Where the quadratic degenerates into a linear equation
+        let gradient = radial(vec2(1., 0.));
+        assert_close(gradient.offset(vec2(0., 0.)).unwrap(), 0.5);
+        assert_close(gradient.offset(vec2(-1., 0.)).unwrap(), 1.);
+        assert_close(gradient.offset(vec2(0., 1.)).unwrap(), 1.);
+        // Behind the focal point no circle passes
+        assert_eq!(gradient.offset(vec2(2., 0.)), None);
+    }
+
+    #[test]
+    fn radial_with_the_focal_point_outside() {
+        let gradient = radial(vec2(2., 0.));
+        assert_close(gradient.offset(vec2(1., 0.)).unwrap(), 1.);
+        assert_close(gradient.offset(vec2(1.5, 0.)).unwrap(), 0.5);
+        // Outside of the cone of circles
+        assert_eq!(gradient.offset(vec2(3., 0.)), None);
+        assert_eq!(gradient.color_at(vec2(3., 0.)), Color::new(0., 0., 0., 0.));
+    }
+
+    #[test]
+    fn conic() {
+        let gradient = Gradient {
+            kind: GradientKind::Conic,
+            ..radial(Vec2::ZERO)
+        };
+        for (p, t) in [
+            (vec2(1., 0.), 0.),
+            (vec2(0., 1.), 0.25),
+            (vec2(-1., 0.), 0.5),
+            (vec2(0., -1.), 0.75),
+            (vec2(1., -1e-3), 1.),
+        ] {
+            let offset = gradient.offset(p).unwrap();
+            assert!((0. ..1.).contains(&offset));
+            assert!((offset - t).abs() < 1e-3, "{offset} != {t} at {p}");
         }
+
+        let shading =
+            GradientShading::conic(Point::new(10., 10.), FRAC_PI_2, [(0., RED), (1., BLUE)]);
+        assert_close(offset_at(&shading, Point::new(10., 20.)).unwrap(), 0.);
+        assert_close(offset_at(&shading, Point::new(0., 10.)).unwrap(), 0.25);
+        assert_close(offset_at(&shading, Point::new(20., 10.)).unwrap(), 0.75);
+    }
+
+    #[test]
+    fn spreads() {
+        for (t, pad, repeat, reflect) in [
+            (0.25, 0.25, 0.25, 0.25),
+            (1.25, 1., 0.25, 0.75),
+            (-0.25, 0., 0.75, 0.25),
+            (2., 1., 0., 0.),
+            (-1.5, 0., 0.5, 0.5),
+        ] {
+            assert_close(Spread::Pad.apply(t), pad);
+            assert_close(Spread::Repeat.apply(t), repeat);
+            assert_close(Spread::Reflect.apply(t), reflect);
+        }
+        let gradient = Gradient {
+            kind: GradientKind::Linear,
+            spread: Spread::Reflect,
+            ..radial(Vec2::ZERO)
+        };
+        assert_eq!(gradient.color_at(vec2(2., 0.)), RED);
+        assert_eq!(gradient.color_at(vec2(1., 0.)), BLUE);
+    }
+
+    #[test]
+    fn sorts_and_clamps_stops() {
+        let shading = GradientShading::linear(
+            Point::new(0., 0.),
+            Point::new(1., 0.),
+            [(1.5, BLUE), (0.5, GREEN), (-1., RED)],
+        );
+        assert_eq!(
+            shading.gradient().unwrap().stops,
+            vec![(0., RED), (0.5, GREEN), (1., BLUE)]
+        );
+        // Stops of a single color are a plain color
+        let shading = GradientShading::linear(
+            Point::new(0., 0.),
+            Point::new(1., 0.),
+            [(0., RED), (1., RED)],
+        );
+        assert!(shading.gradient().is_none());
+        assert_eq!(
+            shading.new_vertex(&Point::new(0.5, 0.), 0.5).color,
+            [255, 0, 0, 127]
+        );
+    }
+
+    #[test]
+    fn colors_between_stops() {
+        let gradient = Gradient {
+            kind: GradientKind::Linear,
+            stops: vec![(0.2, RED), (0.5, RED), (0.5, BLUE), (0.8, GREEN)],
+            spread: Spread::Pad,
+            space: ColorSpace::Srgb,
+        };
+        assert_eq!(gradient.color(0.), RED);
+        assert_eq!(gradient.color(0.49), RED);
+        // A hard edge
+        assert_eq!(gradient.color(0.51).b, 1. - 0.01 / 0.3);
+        let middle = gradient.color(0.65);
+        assert_close(middle.b, 0.5);
+        assert_close(middle.g, 0.5);
+        assert_eq!(gradient.color(1.), GREEN);
+        let empty = Gradient {
+            stops: Vec::new(),
+            ..gradient
+        };
+        assert_eq!(empty.color(0.5), Color::new(0., 0., 0., 0.));
     }
 }
@@ -0,0 +1,105 @@
+use std::f32::consts::FRAC_PI_2;
+
+use lyon::{
+    math::{Box2D, Point, point, vector},
+    path::builder::BorderRadii,
+};
+use macroquad::math::Vec2;
+
+/// A soft shadow, in logical pixels of the final coordinate system.
+#[derive(Clone, Debug, Default)]
+pub struct Shadow {
+    pub offset: Vec2,
+    /// How far the shadow fades out, like the blur radius of a CSS `box-shadow`.
+    pub blur: f32,
+    /// Grows (or, for rounded rectangles, shrinks) the shape before blurring.
+    pub spread: f32,
+}
+
+impl Shadow {
+    pub fn new(offset: Vec2, blur: f32) -> Self {
+        Self {
+            offset,
+            blur,
+            spread: 0.0,
+        }
+    }
+
+    pub fn with_spread(mut self, spread: f32) -> Self {
+        self.spread = spread;
+        self
+    }
+
+    /// How far the shadow reaches beyond the shape, offset aside.
+    pub fn extent(&self) -> f32 {
+        self.spread.max(0.0) + self.blur * 1.5
+    }
+}
+
+/// Mesh approximating the shadow of a rounded rectangle with a linear falloff,
+/// as `(position, alpha)` vertices and triangle indices.
+///
+/// `spread` and `blur` are in the coordinate system of `rect`,
+/// every corner is approximated by `segments` segments.
+pub(crate) fn box_shadow_mesh(
+    rect: &Box2D,
+    radii: &BorderRadii,
+    spread: f32,
+    blur: f32,
+    segments: usize,
+) -> (Vec<(Point, f32)>, Vec<u32>) {
+    let center = rect.center();
+    let half = vector(
+        (rect.width() / 2.0 + spread).max(0.0),
+        (rect.height() / 2.0 + spread).max(0.0),
+    );
+    // Half the width of the falloff, which matches the slope of a gaussian of
+    // standard deviation blur / 2 in its middle
+    let ramp = blur * 0.625;
+    let inner_ramp = ramp.min(half.x).min(half.y);
+    let inner_alpha = if ramp > 0.0 {
+        (0.5 + inner_ramp / (2.0 * ramp)).min(1.0)
+    } else {
+        1.0
+    };
+    let max_radius = half.x.min(half.y);
+    // (radius, direction of the corner, starting angle), clockwise from the top left
+    let corners = [
+        (radii.top_left, vector(-1.0, -1.0), 2.0 * FRAC_PI_2),
+        (radii.top_right, vector(1.0, -1.0), 3.0 * FRAC_PI_2),
+        (radii.bottom_right, vector(1.0, 1.0), 0.0),
+        (radii.bottom_left, vector(-1.0, 1.0), FRAC_PI_2),
+    ];
+    let segments = segments.max(1);
+    let mut vertices = vec![(center, inner_alpha)];
+    for (radius, direction, start) in corners {
+        let radius = (radius + spread).clamp(0.0, max_radius);
+        let arc_center = center
+            + vector(
+                direction.x * (half.x - radius),
+                direction.y * (half.y - radius),
+            );
+        for i in 0..=segments {
+            let angle = start + FRAC_PI_2 * i as f32 / segments as f32;
+            let (sin, cos) = angle.sin_cos();
+            let inner = if radius >= inner_ramp {
+                arc_center + vector(cos, sin) * (radius - inner_ramp)
+            } else {
+                arc_center + direction * (radius - inner_ramp)
+            };
+            let outer = arc_center + vector(cos, sin) * (radius + ramp);
+            vertices.push((point(inner.x, inner.y), inner_alpha));
+            vertices.push((point(outer.x, outer.y), 0.0));
+        }
+    }
+    let rim = (vertices.len() as u32 - 1) / 2;
+    let mut indices = Vec::with_capacity(rim as usize * 9);
+    for i in 0..rim {
+        let next = (i + 1) % rim;
+        let (inner, outer) = (1 + i * 2, 2 + i * 2);
+        let (next_inner, next_outer) = (1 + next * 2, 2 + next * 2);
+        indices.extend_from_slice(&[0, inner, next_inner]);
+        indices.extend_from_slice(&[inner, outer, next_outer, inner, next_outer, next_inner]);
+    }
+    (vertices, indices)
+}
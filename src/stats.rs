@@ -0,0 +1,38 @@
+//! Per-frame rendering counters, see [`FrameStats`].
+
+use std::time::Duration;
+
+/// What went into rendering one frame, from [`Window::frame_stats`](crate::window::Window::frame_stats).
+///
+/// A frame ends with every [`Window::update`](crate::window::Window::update).
+#[derive(Clone, Debug, Default)]
+pub struct FrameStats {
+    /// Counts frames from 1, 0 before the first one ended
+    pub frame: u64,
+    /// Time between the end of the previous frame and the end of this one
+    pub frame_time: Duration,
+    /// Submissions to the backend, after batching
+    pub draw_calls: u32,
+    pub vertices: usize,
+    pub indices: usize,
+    /// Spent tessellating paths and clips, cached tessellations cost nothing
+    pub tessellation_time: Duration,
+    /// Glyphs that were already in the atlas
+    pub glyph_hits: u32,
+    /// Glyphs that had to be rasterized and uploaded
+    pub glyph_misses: u32,
+    /// Glyphs removed from the atlas to make room for others
+    pub glyph_evictions: u32,
+    /// Fraction of the glyph atlas in use at the end of the frame
+    pub atlas_occupancy: f32,
+    /// Labels whose text had to be shaped and laid out again
+    pub labels_laid_out: u32,
+}
+
+impl FrameStats {
+    /// Frames per second, judging from this frame alone.
+    pub fn fps(&self) -> f32 {
+        let seconds = self.frame_time.as_secs_f32();
+        if seconds > 0.0 { 1.0 / seconds } else { 0.0 }
+    }
+}
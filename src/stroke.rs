@@ -0,0 +1,331 @@
+//! How paths are stroked, see [`StrokeStyle`].
+
+use lyon::{
+    math::Point,
+    path::{Path, PathEvent, iterator::PathIterator},
+    tessellation::StrokeOptions,
+};
+
+pub use lyon::tessellation::{LineCap, LineJoin};
+
+/// Width, caps, joins and dashes of a stroke, passed to every stroke call.
+///
+/// A plain `f32` converts into a solid stroke of that width.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StrokeStyle {
+    /// In the coordinate system the path is drawn in, like the dashes
+    pub width: f32,
+    pub start_cap: LineCap,
+    pub end_cap: LineCap,
+    pub join: LineJoin,
+    pub miter_limit: f32,
+    /// Lengths of alternating dashes and gaps, repeated along every subpath.
+    /// Empty for a solid stroke, an odd count is repeated twice like in SVG.
+    pub dashes: Vec<f32>,
+    /// How far into the dash pattern subpaths start. Animate it for marching ants.
+    ///
+    /// [`Window::stroke_local_path`](crate::window::Window::stroke_local_path) caches a
+    /// tessellation per offset, so animated offsets fill the cache with entries used once.
+    /// Draw those with [`Window::stroke_path`](crate::window::Window::stroke_path) instead.
+    pub dash_offset: f32,
+}
+
+impl Default for StrokeStyle {
+    fn default() -> Self {
+        Self::new(1.0)
+    }
+}
+
+impl From<f32> for StrokeStyle {
+    fn from(width: f32) -> Self {
+        Self::new(width)
+    }
+}
+
+impl From<&StrokeStyle> for StrokeStyle {
+    fn from(style: &StrokeStyle) -> Self {
+        style.clone()
+    }
+}
+
+impl StrokeStyle {
+    pub fn new(width: f32) -> Self {
+        Self {
+            width,
+            start_cap: LineCap::Butt,
+            end_cap: LineCap::Butt,
+            join: LineJoin::Miter,
+            miter_limit: StrokeOptions::DEFAULT_MITER_LIMIT,
+            dashes: Vec::new(),
+            dash_offset: 0.0,
+        }
+    }
+
+    /// Sets both caps.
+    pub fn with_cap(mut self, cap: LineCap) -> Self {
+        self.start_cap = cap;
+        self.end_cap = cap;
+        self
+    }
+
+    pub fn with_join(mut self, join: LineJoin) -> Self {
+        self.join = join;
+        self
+    }
+
+    pub fn with_miter_limit(mut self, miter_limit: f32) -> Self {
+        self.miter_limit = miter_limit;
+        self
+    }
+
+    pub fn with_dashes(mut self, dashes: impl Into<Vec<f32>>) -> Self {
+        self.dashes = dashes.into();
+        self
+    }
+
+    pub fn with_dash_offset(mut self, dash_offset: f32) -> Self {
+        self.dash_offset = dash_offset;
+        self
+    }
+
+    pub(crate) fn options(&self, tolerance: f32) -> StrokeOptions {
+        StrokeOptions::tolerance(tolerance)
+            .with_line_width(self.width)
+            .with_start_cap(self.start_cap)
+            .with_end_cap(self.end_cap)
+            .with_line_join(self.join)
+            .with_miter_limit(self.miter_limit.max(StrokeOptions::MINIMUM_MITER_LIMIT))
+    }
+
+    /// Whether the dashes make up a usable pattern, invalid ones draw solid strokes.
+    pub(crate) fn is_dashed(&self) -> bool {
+        !self.dashes.is_empty()
+            && self.dashes.iter().all(|&d| d >= 0.0 && d.is_finite())
+            && self.dashes.iter().sum::<f32>() > 0.0
+    }
+
+    /// The dashes of `path`, curves flattened to within `tolerance`, as open subpaths.
+    pub(crate) fn dash(&self, path: &Path, tolerance: f32) -> Path {
+        let mut pattern = self.dashes.clone();
+        if pattern.len() % 2 == 1 {
+            pattern.extend_from_within(..);
+        }
+        let total: f32 = pattern.iter().sum();
+        // Where in the pattern every subpath starts
+        let mut start = (0, pattern[0]);
+        let mut offset = self.dash_offset.rem_euclid(total);
+        for (i, &length) in pattern.iter().enumerate() {
+            // Zero length dashes at the start are dots
+            if offset < length || offset == 0.0 {
+                start = (i, length - offset);
+                break;
+            }
+            offset -= length;
+        }
+
+        let mut builder = Path::builder();
+        let mut dasher = Dasher {
+            pattern: &pattern,
+            index: start.0,
+            remaining: start.1,
+            drawing: false,
+        };
+        for event in path.iter().flattened(tolerance) {
+            match event {
+                PathEvent::Begin { .. } => {
+                    (dasher.index, dasher.remaining) = start;
+                }
+                PathEvent::Line { from, to } => dasher.segment(&mut builder, from, to),
+                PathEvent::End { last, first, close } => {
+                    if close {
+                        dasher.segment(&mut builder, last, first);
+                    }
+                    if dasher.drawing {
+                        builder.end(false);
+                        dasher.drawing = false;
+                    }
+                }
+                // Flattened away
+                PathEvent::Quadratic { .. } | PathEvent::Cubic { .. } => {}
+            }
+        }
+        builder.build()
+    }
+}
+
+/// Position in a dash pattern while walking along a path.
+struct Dasher<'a> {
+    pattern: &'a [f32],
+    index: usize,
+    /// Of the current dash or gap
+    remaining: f32,
+    /// Whether a dash has been begun in the builder
+    drawing: bool,
+}
+
+impl Dasher<'_> {
+    fn segment(&mut self, builder: &mut lyon::path::path::Builder, from: Point, to: Point) {
+        let length = (to - from).length();
+        let mut done = 0.0;
+        loop {
+            let on = self.index.is_multiple_of(2);
+            if on && !self.drawing {
+                builder.begin(from.lerp(to, done / length.max(f32::MIN_POSITIVE)));
+                self.drawing = true;
+            }
+            let step = self.remaining.min(length - done);
+            done += step;
+            self.remaining -= step;
+            if on {
+                builder.line_to(from.lerp(to, done / length.max(f32::MIN_POSITIVE)));
+            }
+            if self.remaining > 0.0 {
+                break;
+            }
+            if on {
+                builder.end(false);
+                self.drawing = false;
+            }
+            self.index = (self.index + 1) % self.pattern.len();
+            self.remaining = self.pattern[self.index];
+            if done >= length && self.remaining > 0.0 {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lyon::math::point;
+
+    use super::*;
+
+    /// Points of every subpath of `path`, dashed by `style`.
+    fn dashes(style: &StrokeStyle, path: &Path) -> Vec<Vec<(f32, f32)>> {
+        let mut subpaths = Vec::new();
+        for event in style.dash(path, 0.01).iter() {
+            match event {
+                PathEvent::Begin { at } => subpaths.push(vec![(at.x, at.y)]),
+                PathEvent::Line { to, .. } => subpaths.last_mut().unwrap().push((to.x, to.y)),
+                PathEvent::End { close, .. } => assert!(!close, "dashes are open"),
+                _ => unreachable!("dashes are flattened"),
+            }
+        }
+        subpaths
+    }
+
+    /// `(start, end)` of every dash along the x axis.
+    fn spans(style: &StrokeStyle, path: &Path) -> Vec<(f32, f32)> {
+        dashes(style, path)
+            .iter()
+            .map(|dash| (dash[0].0, dash.last().unwrap().0))
+            .collect()
+    }
+
+    fn assert_close(actual: &[(f32, f32)], expected: &[(f32, f32)]) {
+        let close = actual.len() == expected.len()
+            && actual
+                .iter()
+                .zip(expected)
+                .all(|(a, b)| (a.0 - b.0).abs() < 1e-4 && (a.1 - b.1).abs() < 1e-4);
+        assert!(close, "{actual:?} != {expected:?}");
+    }
+
+    fn polyline(points: &[(f32, f32)], close: bool) -> Path {
+        let mut builder = Path::builder();
+        builder.begin(point(points[0].0, points[0].1));
+        for &(x, y) in &points[1..] {
+            builder.line_to(point(x, y));
+        }
+        builder.end(close);
+        builder.build()
+    }
+
+    fn line(length: f32) -> Path {
+        polyline(&[(0., 0.), (length, 0.)], false)
+    }
+
+    #[test]
+    fn dashes_a_line() {
+        let style = StrokeStyle::new(1.).with_dashes([2., 1.]);
+        assert_close(
+            &spans(&style, &line(10.)),
+            &[(0., 2.), (3., 5.), (6., 8.), (9., 10.)],
+        );
+    }
+
+    #[test]
+    fn wraps_the_offset_around() {
+        let style = StrokeStyle::new(1.).with_dashes([2., 1.]);
+        let expected = [(0., 1.), (2., 4.), (5., 7.), (8., 10.)];
+        for offset in [1., 4., -2., 301.] {
+            let style = style.clone().with_dash_offset(offset);
+            assert_close(&spans(&style, &line(10.)), &expected);
+        }
+        // Starting in a gap
+        let style = style.with_dash_offset(2.5);
+        assert_close(&spans(&style, &line(5.)), &[(0.5, 2.5), (3.5, 5.)]);
+    }
+
+    #[test]
+    fn repeats_odd_patterns() {
+        let style = StrokeStyle::new(1.).with_dashes([2.]);
+        assert_close(&spans(&style, &line(10.)), &[(0., 2.), (4., 6.), (8., 10.)]);
+        let style = StrokeStyle::new(1.).with_dashes([1., 2., 3.]);
+        assert_close(&spans(&style, &line(12.)), &[(0., 1.), (3., 6.), (7., 9.)]);
+    }
+
+    #[test]
+    fn keeps_zero_length_dashes() {
+        // Dots, once drawn with round caps
+        let style = StrokeStyle::new(1.).with_dashes([0., 2.]);
+        assert_close(&spans(&style, &line(5.)), &[(0., 0.), (2., 2.), (4., 4.)]);
+    }
+
+    #[test]
+    fn continues_dashes_across_joints() {
+        let style = StrokeStyle::new(1.).with_dashes([4., 1.]);
+        let path = polyline(&[(0., 0.), (3., 0.), (3., 3.)], false);
+        assert_eq!(
+            dashes(&style, &path),
+            vec![vec![(0., 0.), (3., 0.), (3., 1.)], vec![(3., 2.), (3., 3.)]]
+        );
+    }
+
+    #[test]
+    fn dashes_the_closing_segment() {
+        let style = StrokeStyle::new(1.).with_dashes([3., 1.]);
+        let mut builder = Path::builder();
+        for origin in [0., 10.] {
+            builder.add_polygon(lyon::path::Polygon {
+                points: &[
+                    point(origin, 0.),
+                    point(origin + 4., 0.),
+                    point(origin + 4., 4.),
+                    point(origin, 4.),
+                ],
+                closed: true,
+            });
+        }
+        let dashes = dashes(&style, &builder.build());
+        // Every subpath starts the pattern again
+        let square = |x: f32| {
+            vec![
+                vec![(x, 0.), (x + 3., 0.)],
+                vec![(x + 4., 0.), (x + 4., 3.)],
+                vec![(x + 4., 4.), (x + 1., 4.)],
+                vec![(x, 4.), (x, 1.)],
+            ]
+        };
+        assert_eq!(dashes, [square(0.), square(10.)].concat());
+    }
+
+    #[test]
+    fn invalid_patterns_are_solid() {
+        for dashes in [vec![], vec![0., 0.], vec![1., -1.], vec![f32::NAN, 1.]] {
+            assert!(!StrokeStyle::new(1.).with_dashes(dashes).is_dashed());
+        }
+        assert!(StrokeStyle::new(1.).with_dashes([0., 1.]).is_dashed());
+    }
+}
@@ -0,0 +1,321 @@
+//! SVG documents as lyon paths, see [`Svg`].
+
+use anyhow::Context;
+use lyon::{
+    math::point,
+    path::{FillRule, Path},
+};
+use macroquad::color::Color;
+use tracing::warn;
+use usvg::tiny_skia_path::PathSegment;
+
+use crate::{
+    shading::GradientShading,
+    stroke::{LineCap, LineJoin, StrokeStyle},
+    utils::{Point, Transform},
+    window::Window,
+};
+
+pub use crate::shading::Spread;
+
+/// How a shape is painted, in its own coordinate system.
+#[derive(Clone, Debug)]
+pub enum SvgPaint {
+    Color(Color),
+    LinearGradient {
+        start: Point,
+        end: Point,
+        /// `(offset, color)`, offsets increasing from 0 to 1
+        stops: Vec<(f32, Color)>,
+        spread: Spread,
+        /// Gradient space -> coordinate system of the shape
+        transform: Transform,
+    },
+    RadialGradient {
+        center: Point,
+        radius: f32,
+        /// Where the gradient starts, inside the circle
+        focal: Point,
+        stops: Vec<(f32, Color)>,
+        spread: Spread,
+        transform: Transform,
+    },
+}
+
+impl SvgPaint {
+    /// Shades the paint, `tr` mapping the coordinate system of the shape to the final one.
+    fn shading(&self, tr: &Transform) -> GradientShading {
+        match self {
+            Self::Color(color) => GradientShading::solid(*color),
+            Self::LinearGradient {
+                start,
+                end,
+                stops,
+                spread,
+                transform,
+            } => GradientShading::linear(*start, *end, stops.as_slice())
+                .with_spread(*spread)
+                .with_transform(&(tr * transform)),
+            Self::RadialGradient {
+                center,
+                radius,
+                focal,
+                stops,
+                spread,
+                transform,
+            } => GradientShading::radial(*center, *radius, stops.as_slice())
+                .with_focal(*focal)
+                .with_spread(*spread)
+                .with_transform(&(tr * transform)),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum SvgStyle {
+    Fill(FillRule),
+    Stroke(StrokeStyle),
+}
+
+/// A single fill or stroke of an SVG document.
+#[derive(Clone, Debug)]
+pub struct SvgShape {
+    pub path: Path,
+    /// Coordinate system of the shape -> coordinate system of the document
+    pub transform: Transform,
+    pub style: SvgStyle,
+    pub paint: SvgPaint,
+    /// Opacity of the fill or stroke and of all enclosing groups
+    pub opacity: f32,
+}
+
+/// The fills and strokes of an SVG document, in painting order.
+///
+/// Images, text, patterns, clips, masks and filters are not supported and skipped.
+/// Group opacity is applied to every shape on its own.
+#[derive(Clone, Debug)]
+pub struct Svg {
+    /// Width and height of the document
+    pub size: (f32, f32),
+    pub shapes: Vec<SvgShape>,
+}
+
+impl std::str::FromStr for Svg {
+    type Err = anyhow::Error;
+
+    fn from_str(text: &str) -> anyhow::Result<Self> {
+        let tree =
+            usvg::Tree::from_str(text, &usvg::Options::default()).context("failed to parse SVG")?;
+        Ok(Self::from_tree(&tree))
+    }
+}
+
+impl Svg {
+    /// Parses an SVG document, possibly gzipped.
+    pub fn from_data(data: &[u8]) -> anyhow::Result<Self> {
+        let tree = usvg::Tree::from_data(data, &usvg::Options::default())
+            .context("failed to parse SVG")?;
+        Ok(Self::from_tree(&tree))
+    }
+
+    pub fn load(path: impl AsRef<std::path::Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let data = std::fs::read(path).with_context(|| format!("failed to read {path:?}"))?;
+        Self::from_data(&data)
+    }
+
+    fn from_tree(tree: &usvg::Tree) -> Self {
+        let mut shapes = Vec::new();
+        add_group(tree.root(), 1., &mut shapes);
+        Self {
+            size: (tree.size().width(), tree.size().height()),
+            shapes,
+        }
+    }
+
+    /// Draws the document, `tr` mapping its coordinate system to the final one.
+    ///
+    /// `recolor` replaces every paint, keeping opacities.
+    pub fn render(&self, tr: &Transform, target: &mut Window, recolor: Option<Color>) {
+        let fill_options = target.fill_options();
+        for shape in &self.shapes {
+            let tr = tr * shape.transform;
+            let shading = match recolor {
+                Some(color) => GradientShading::solid(color),
+                None => shape.paint.shading(&tr),
+            };
+            match &shape.style {
+                SvgStyle::Fill(rule) => {
+                    target.set_fill_options(|options| options.with_fill_rule(*rule));
+                    target.fill_local_path(&shape.path, &tr, shading, shape.opacity);
+                }
+                SvgStyle::Stroke(style) => {
+                    target.stroke_local_path(&shape.path, &tr, shading, shape.opacity, style);
+                }
+            }
+        }
+        target.set_fill_options(|_| fill_options);
+    }
+}
+
+fn add_group(group: &usvg::Group, opacity: f32, shapes: &mut Vec<SvgShape>) {
+    let opacity = opacity * group.opacity().get();
+    if group.clip_path().is_some() || group.mask().is_some() || !group.filters().is_empty() {
+        warn!("SVG clips, masks and filters are not supported");
+    }
+    for node in group.children() {
+        match node {
+            usvg::Node::Group(group) => add_group(group, opacity, shapes),
+            usvg::Node::Path(path) => add_path(path, opacity, shapes),
+            usvg::Node::Image(_) | usvg::Node::Text(_) => {
+                warn!("SVG images and text are not supported");
+            }
+        }
+    }
+}
+
+fn add_path(path: &usvg::Path, opacity: f32, shapes: &mut Vec<SvgShape>) {
+    if !path.is_visible() {
+        return;
+    }
+    let lyon_path = convert_path(path.data());
+    let transform = convert_transform(&path.abs_transform());
+    let fill = path.fill().and_then(|fill| {
+        Some(SvgShape {
+            path: lyon_path.clone(),
+            transform,
+            style: SvgStyle::Fill(match fill.rule() {
+                usvg::FillRule::NonZero => FillRule::NonZero,
+                usvg::FillRule::EvenOdd => FillRule::EvenOdd,
+            }),
+            paint: convert_paint(fill.paint())?,
+            opacity: opacity * fill.opacity().get(),
+        })
+    });
+    let stroke = path.stroke().and_then(|stroke| {
+        let cap = match stroke.linecap() {
+            usvg::LineCap::Butt => LineCap::Butt,
+            usvg::LineCap::Round => LineCap::Round,
+            usvg::LineCap::Square => LineCap::Square,
+        };
+        Some(SvgShape {
+            path: lyon_path.clone(),
+            transform,
+            style: SvgStyle::Stroke(StrokeStyle {
+                width: stroke.width().get(),
+                start_cap: cap,
+                end_cap: cap,
+                join: match stroke.linejoin() {
+                    usvg::LineJoin::Miter => LineJoin::Miter,
+                    usvg::LineJoin::MiterClip => LineJoin::MiterClip,
+                    usvg::LineJoin::Round => LineJoin::Round,
+                    usvg::LineJoin::Bevel => LineJoin::Bevel,
+                },
+                miter_limit: stroke.miterlimit().get(),
+                dashes: stroke.dasharray().map(<[f32]>::to_vec).unwrap_or_default(),
+                dash_offset: stroke.dashoffset(),
+            }),
+            paint: convert_paint(stroke.paint())?,
+            opacity: opacity * stroke.opacity().get(),
+        })
+    });
+    let (first, second) = match path.paint_order() {
+        usvg::PaintOrder::FillAndStroke => (fill, stroke),
+        usvg::PaintOrder::StrokeAndFill => (stroke, fill),
+    };
+    shapes.extend(first);
+    shapes.extend(second);
+}
+
+fn convert_path(data: &usvg::tiny_skia_path::Path) -> Path {
+    let mut builder = Path::builder();
+    let mut open = false;
+    for segment in data.segments() {
+        match segment {
+            PathSegment::MoveTo(p) => {
+                if open {
+                    builder.end(false);
+                }
+                builder.begin(point(p.x, p.y));
+                open = true;
+            }
+            PathSegment::LineTo(p) => {
+                builder.line_to(point(p.x, p.y));
+            }
+            PathSegment::QuadTo(ctrl, p) => {
+                builder.quadratic_bezier_to(point(ctrl.x, ctrl.y), point(p.x, p.y));
+            }
+            PathSegment::CubicTo(ctrl1, ctrl2, p) => {
+                builder.cubic_bezier_to(
+                    point(ctrl1.x, ctrl1.y),
+                    point(ctrl2.x, ctrl2.y),
+                    point(p.x, p.y),
+                );
+            }
+            PathSegment::Close => {
+                if open {
+                    builder.end(true);
+                    open = false;
+                }
+            }
+        }
+    }
+    if open {
+        builder.end(false);
+    }
+    builder.build()
+}
+
+fn convert_transform(tr: &usvg::Transform) -> Transform {
+    Transform::new(tr.sx, tr.kx, tr.tx, tr.ky, tr.sy, tr.ty, 0., 0., 1.)
+}
+
+fn convert_paint(paint: &usvg::Paint) -> Option<SvgPaint> {
+    let stops = |gradient: &usvg::BaseGradient| {
+        gradient
+            .stops()
+            .iter()
+            .map(|stop| {
+                let color = stop.color();
+                (
+                    stop.offset().get(),
+                    Color::from_rgba(
+                        color.red,
+                        color.green,
+                        color.blue,
+                        (stop.opacity().get() * 255.).round() as u8,
+                    ),
+                )
+            })
+            .collect()
+    };
+    let spread = |gradient: &usvg::BaseGradient| match gradient.spread_method() {
+        usvg::SpreadMethod::Pad => Spread::Pad,
+        usvg::SpreadMethod::Repeat => Spread::Repeat,
+        usvg::SpreadMethod::Reflect => Spread::Reflect,
+    };
+    Some(match paint {
+        usvg::Paint::Color(color) => {
+            SvgPaint::Color(Color::from_rgba(color.red, color.green, color.blue, 255))
+        }
+        usvg::Paint::LinearGradient(gradient) => SvgPaint::LinearGradient {
+            start: Point::new(gradient.x1(), gradient.y1()),
+            end: Point::new(gradient.x2(), gradient.y2()),
+            stops: stops(gradient),
+            spread: spread(gradient),
+            transform: convert_transform(&gradient.transform()),
+        },
+        usvg::Paint::RadialGradient(gradient) => SvgPaint::RadialGradient {
+            center: Point::new(gradient.cx(), gradient.cy()),
+            radius: gradient.r().get(),
+            focal: Point::new(gradient.fx(), gradient.fy()),
+            stops: stops(gradient),
+            spread: spread(gradient),
+            transform: convert_transform(&gradient.transform()),
+        },
+        usvg::Paint::Pattern(_) => {
+            warn!("SVG patterns are not supported");
+            return None;
+        }
+    })
+}
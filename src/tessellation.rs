@@ -0,0 +1,430 @@
+//! Caching of tessellated lyon paths, see [`TessellationCache`].
+
+use std::{
+    hash::{DefaultHasher, Hash, Hasher},
+    num::NonZeroUsize,
+};
+
+use lru::LruCache;
+use lyon::{
+    algorithms::hit_test::hit_test_path,
+    math::{Point, Vector},
+    path::{Path, PathEvent},
+    tessellation::{
+        BuffersBuilder, FillOptions, LineJoin, Side, StrokeOptions, StrokeTessellator,
+        StrokeVertex, VertexBuffers,
+    },
+};
+use tracing::warn;
+
+use crate::stroke::StrokeStyle;
+
+/// Triangles in the coordinate system of the path they were made from.
+#[derive(Clone, Debug, Default)]
+pub struct Geometry {
+    pub vertices: Vec<Point>,
+    pub indices: Vec<u16>,
+    /// For anti-aliased geometry, where every vertex moves for a fringe one unit wide,
+    /// vertices that move fading out completely. Empty otherwise.
+    pub outsets: Vec<Vector>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl CacheStats {
+    /// Fraction of lookups that were hits, 0 if there were none.
+    pub fn hit_rate(&self) -> f32 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            0.0
+        } else {
+            self.hits as f32 / total as f32
+        }
+    }
+}
+
+/// Least recently used tessellations, keyed by a hash of the path and the tessellation options.
+///
+/// Both the number of entries and the total number of vertices are limited.
+pub struct TessellationCache {
+    entries: LruCache<u64, Geometry>,
+    max_vertices: usize,
+    vertices: usize,
+    stats: CacheStats,
+}
+
+impl Default for TessellationCache {
+    fn default() -> Self {
+        Self::new(256, 1 << 18)
+    }
+}
+
+impl TessellationCache {
+    pub fn new(max_entries: usize, max_vertices: usize) -> Self {
+        Self {
+            entries: LruCache::new(NonZeroUsize::new(max_entries).unwrap_or(NonZeroUsize::MIN)),
+            max_vertices,
+            vertices: 0,
+            stats: CacheStats::default(),
+        }
+    }
+
+    /// Changes the limits, evicting entries as needed.
+    pub fn set_limits(&mut self, max_entries: usize, max_vertices: usize) {
+        self.max_vertices = max_vertices;
+        while self.entries.len() > max_entries {
+            self.pop_lru();
+        }
+        self.entries
+            .resize(NonZeroUsize::new(max_entries).unwrap_or(NonZeroUsize::MIN));
+        self.evict();
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.stats
+    }
+
+    pub fn reset_stats(&mut self) {
+        self.stats = CacheStats::default();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Total number of cached vertices.
+    pub fn vertices(&self) -> usize {
+        self.vertices
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.vertices = 0;
+    }
+
+    /// Looks up `key`, counting a hit or a miss and marking the entry as recently used.
+    pub(crate) fn get(&mut self, key: u64) -> Option<&Geometry> {
+        if self.entries.contains(&key) {
+            self.stats.hits += 1;
+            self.entries.get(&key)
+        } else {
+            self.stats.misses += 1;
+            None
+        }
+    }
+
+    /// Looks up `key` without touching the statistics or the order of entries.
+    pub(crate) fn peek(&self, key: u64) -> Option<&Geometry> {
+        self.entries.peek(&key)
+    }
+
+    pub(crate) fn insert(&mut self, key: u64, geometry: Geometry) {
+        self.vertices += geometry.vertices.len();
+        if let Some((_, evicted)) = self.entries.push(key, geometry) {
+            self.vertices -= evicted.vertices.len();
+        }
+        self.evict();
+    }
+
+    /// Drops the least recently used entries until the vertex limit is met,
+    /// always keeping the most recent one.
+    fn evict(&mut self) {
+        while self.vertices > self.max_vertices && self.entries.len() > 1 {
+            self.pop_lru();
+        }
+    }
+
+    fn pop_lru(&mut self) {
+        if let Some((_, evicted)) = self.entries.pop_lru() {
+            self.vertices -= evicted.vertices.len();
+        }
+    }
+}
+
+/// Adds a fringe along the outline of `path`, which `geometry` is the fill of,
+/// fading out towards the outside. See [`Geometry::outsets`].
+pub(crate) fn add_fringe(
+    tessellator: &mut StrokeTessellator,
+    path: &Path,
+    options: &FillOptions,
+    geometry: &mut Geometry,
+) {
+    geometry
+        .outsets
+        .resize(geometry.vertices.len(), Vector::zero());
+    let stroke_options = StrokeOptions::tolerance(options.tolerance)
+        .with_line_join(LineJoin::Miter)
+        .with_line_width(2.0);
+    let mut buffers: VertexBuffers<(Point, Vector, Side), u16> = VertexBuffers::new();
+    // Every subpath separately, as the side the fill is on may differ between them
+    for subpath in subpaths(path) {
+        buffers.vertices.clear();
+        buffers.indices.clear();
+        let result = tessellator.tessellate_path(
+            &subpath,
+            &stroke_options,
+            &mut BuffersBuilder::new(&mut buffers, |v: StrokeVertex| {
+                (v.position_on_path(), v.normal(), v.side())
+            }),
+        );
+        if let Err(e) = result {
+            warn!("Failed to tessellate fringe: {:?}", e);
+            continue;
+        }
+        let Some(inside) = inner_side(path, options, &buffers.vertices) else {
+            continue;
+        };
+        let base = geometry.vertices.len();
+        if base + buffers.vertices.len() > u16::MAX as usize + 1 {
+            warn!("Path too large for anti-aliasing");
+            return;
+        }
+        for &(position, normal, side) in &buffers.vertices {
+            geometry.vertices.push(position);
+            geometry.outsets.push(if side == inside {
+                Vector::zero()
+            } else {
+                normal
+            });
+        }
+        geometry
+            .indices
+            .extend(buffers.indices.iter().map(|&i| i + base as u16));
+    }
+}
+
+/// Which side of an outline stroked into `vertices` the fill of `path` is on,
+/// by majority over a few of the vertices.
+fn inner_side(
+    path: &Path,
+    options: &FillOptions,
+    vertices: &[(Point, Vector, Side)],
+) -> Option<Side> {
+    const SAMPLES: usize = 8;
+    let positive: Vec<_> = vertices
+        .iter()
+        .filter(|(_, _, side)| *side == Side::Positive)
+        .collect();
+    if positive.is_empty() {
+        return None;
+    }
+    let step = positive.len().div_ceil(SAMPLES);
+    let (mut inside, mut total) = (0, 0);
+    for (position, normal, _) in positive.into_iter().step_by(step) {
+        let probe = *position + normal.normalize() * options.tolerance * 2.0;
+        if hit_test_path(&probe, path.iter(), options.fill_rule, options.tolerance) {
+            inside += 1;
+        }
+        total += 1;
+    }
+    Some(if inside * 2 >= total {
+        Side::Positive
+    } else {
+        Side::Negative
+    })
+}
+
+/// The subpaths of `path`, all closed, as they are when filling.
+fn subpaths(path: &Path) -> Vec<Path> {
+    let mut subpaths = Vec::new();
+    let mut builder = Path::builder();
+    for event in path.iter() {
+        match event {
+            PathEvent::Begin { at } => {
+                builder.begin(at);
+            }
+            PathEvent::Line { to, .. } => {
+                builder.line_to(to);
+            }
+            PathEvent::Quadratic { ctrl, to, .. } => {
+                builder.quadratic_bezier_to(ctrl, to);
+            }
+            PathEvent::Cubic {
+                ctrl1, ctrl2, to, ..
+            } => {
+                builder.cubic_bezier_to(ctrl1, ctrl2, to);
+            }
+            PathEvent::End { .. } => {
+                builder.end(true);
+                subpaths.push(std::mem::replace(&mut builder, Path::builder()).build());
+            }
+        }
+    }
+    subpaths
+}
+
+fn hash_path(path: &Path, hasher: &mut impl Hasher) {
+    for event in path.iter() {
+        let (tag, points): (u8, &[Point]) = match &event {
+            PathEvent::Begin { at } => (0, std::slice::from_ref(at)),
+            PathEvent::Line { to, .. } => (1, std::slice::from_ref(to)),
+            PathEvent::Quadratic { ctrl, to, .. } => (2, &[*ctrl, *to]),
+            PathEvent::Cubic {
+                ctrl1, ctrl2, to, ..
+            } => (3, &[*ctrl1, *ctrl2, *to]),
+            PathEvent::End { close, .. } => (if *close { 5 } else { 4 }, &[]),
+        };
+        tag.hash(hasher);
+        for p in points {
+            p.x.to_bits().hash(hasher);
+            p.y.to_bits().hash(hasher);
+        }
+    }
+}
+
+pub(crate) fn fill_key(path: &Path, options: &FillOptions, anti_alias: bool) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    0u8.hash(&mut hasher);
+    anti_alias.hash(&mut hasher);
+    hash_path(path, &mut hasher);
+    options.tolerance.to_bits().hash(&mut hasher);
+    (options.fill_rule as u8).hash(&mut hasher);
+    (options.sweep_orientation as u8).hash(&mut hasher);
+    options.handle_intersections.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// `options` are those `style` makes, dashes aside.
+pub(crate) fn stroke_key(path: &Path, options: &StrokeOptions, style: &StrokeStyle) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    1u8.hash(&mut hasher);
+    hash_path(path, &mut hasher);
+    options.tolerance.to_bits().hash(&mut hasher);
+    options.line_width.to_bits().hash(&mut hasher);
+    options.miter_limit.to_bits().hash(&mut hasher);
+    (options.start_cap as u8).hash(&mut hasher);
+    (options.end_cap as u8).hash(&mut hasher);
+    (options.line_join as u8).hash(&mut hasher);
+    options.variable_line_width.hash(&mut hasher);
+    if style.is_dashed() {
+        for dash in &style.dashes {
+            dash.to_bits().hash(&mut hasher);
+        }
+        style.dash_offset.to_bits().hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use lyon::path::Winding;
+    use macroquad::color::Color;
+    use nalgebra::Vector2;
+
+    use super::*;
+    use crate::{
+        backend::RecordingBackend, shading::IntoShading, utils::Transform, window::Window,
+    };
+
+    fn geometry(vertices: usize) -> Geometry {
+        Geometry {
+            vertices: vec![Point::zero(); vertices],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn counts_hits_and_misses() {
+        let mut cache = TessellationCache::new(4, 100);
+        assert_eq!(cache.stats().hit_rate(), 0.0);
+        assert!(cache.get(1).is_none());
+        cache.insert(1, geometry(3));
+        assert_eq!(cache.get(1).unwrap().vertices.len(), 3);
+        assert!(cache.get(1).is_some());
+        assert!(cache.peek(2).is_none());
+        assert_eq!(cache.stats(), CacheStats { hits: 2, misses: 1 });
+        assert!((cache.stats().hit_rate() - 2. / 3.).abs() < 1e-6);
+        cache.reset_stats();
+        assert_eq!(cache.stats(), CacheStats::default());
+    }
+
+    #[test]
+    fn evicts_at_the_entry_limit() {
+        let mut cache = TessellationCache::new(2, 100);
+        cache.insert(1, geometry(3));
+        cache.insert(2, geometry(3));
+        // 1 becomes the most recently used
+        cache.get(1);
+        cache.insert(3, geometry(3));
+        assert!(cache.peek(1).is_some() && cache.peek(2).is_none() && cache.peek(3).is_some());
+        assert_eq!((cache.len(), cache.vertices()), (2, 6));
+
+        cache.set_limits(1, 100);
+        assert_eq!(cache.len(), 1);
+        assert!(cache.peek(3).is_some());
+        assert_eq!(cache.vertices(), 3);
+    }
+
+    #[test]
+    fn evicts_at_the_vertex_limit() {
+        let mut cache = TessellationCache::new(10, 10);
+        cache.insert(1, geometry(4));
+        cache.insert(2, geometry(4));
+        cache.insert(3, geometry(4));
+        assert!(cache.peek(1).is_none());
+        assert_eq!((cache.len(), cache.vertices()), (2, 8));
+        // Replacing an entry doesn't count it twice
+        cache.insert(3, geometry(2));
+        assert_eq!((cache.len(), cache.vertices()), (2, 6));
+        // The newest entry stays, however large
+        cache.insert(4, geometry(50));
+        assert_eq!((cache.len(), cache.vertices()), (1, 50));
+        cache.set_limits(10, 0);
+        assert_eq!(cache.len(), 1);
+        cache.clear();
+        assert_eq!((cache.len(), cache.vertices()), (0, 0));
+    }
+
+    /// Fills a circle with `tr`, returning the entries and misses of the cache afterwards.
+    fn fill_circle(window: &mut Window, tr: &Transform) -> (usize, u64) {
+        let mut builder = Path::builder();
+        builder.add_circle(Point::new(0., 0.), 1., Winding::Positive);
+        window.fill_local_path(
+            &builder.build(),
+            tr,
+            Color::new(1., 1., 1., 1.).into_shading(),
+            1.,
+        );
+        let cache = window.tessellation_cache();
+        (cache.len(), cache.stats().misses)
+    }
+
+    fn at(x: f32, y: f32, scale: f32) -> Transform {
+        Transform::new_translation(&Vector2::new(x, y)) * Transform::new_scaling(scale)
+    }
+
+    #[test]
+    fn reuses_tessellations_when_translated() {
+        let mut window = Window::new(RecordingBackend::new(100., 100.));
+        assert_eq!(fill_circle(&mut window, &at(10., 10., 3.)), (1, 1));
+        assert_eq!(fill_circle(&mut window, &at(60., 30., 3.)), (1, 1));
+        // Within the same power of two
+        assert_eq!(fill_circle(&mut window, &at(0., 0., 4.)), (1, 1));
+        assert_eq!(window.tessellation_cache().stats().hits, 2);
+    }
+
+    #[test]
+    fn tessellates_again_when_the_scale_crosses_a_power_of_two() {
+        let mut window = Window::new(RecordingBackend::new(100., 100.));
+        fill_circle(&mut window, &at(0., 0., 4.));
+        assert_eq!(fill_circle(&mut window, &at(0., 0., 4.5)), (2, 2));
+        // Finer when larger, most recent first
+        let sizes = window
+            .tessellation_cache()
+            .entries
+            .iter()
+            .map(|(_, geometry)| geometry.vertices.len())
+            .collect::<Vec<_>>();
+        assert!(sizes[0] > sizes[1], "{sizes:?}");
+        // Shrinking back reuses the coarser one
+        assert_eq!(fill_circle(&mut window, &at(0., 0., 3.5)), (2, 2));
+    }
+}
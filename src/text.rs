@@ -10,11 +10,12 @@ use guillotiere::{
 use lru::LruCache;
 use macroquad::{
     math::Rect,
-    miniquad::native::gl,
-    texture::{Image, Texture2D, render_target},
+    texture::{Image, Texture2D},
 };
 use tracing::trace;
 
+use crate::backend::{MacroquadBackend, RenderBackend};
+
 /// For weird rect like 1x0
 enum CAllocation {
     Real(Allocation),
@@ -49,6 +50,7 @@ impl CAllocation {
 fn alloc_or_evict(
     allocator: &mut AtlasAllocator,
     cache: &mut LruCache<CacheKey, (CAllocId, Placement)>,
+    evictions: &mut u64,
     size: Size2D<i32, UnknownUnit>,
 ) -> CAllocation {
     if size.width <= 0 || size.height <= 0 {
@@ -62,8 +64,11 @@ fn alloc_or_evict(
             "Failed to allocate space of {}x{} in the atlas, evicting one item",
             size.width, size.height
         );
-        if let Some((_, (CAllocId::Real(id), _))) = cache.pop_lru() {
-            allocator.deallocate(id);
+        if let Some((_, (id, _))) = cache.pop_lru() {
+            *evictions += 1;
+            if let CAllocId::Real(id) = id {
+                allocator.deallocate(id);
+            }
         }
     }
     // TODO: handle this better
@@ -79,26 +84,50 @@ fn alloc_or_evict(
 pub struct Atlas {
     allocator: AtlasAllocator,
     pub texture: Texture2D,
+    /// Width and height of [`Self::texture`], in pixels
+    length: u32,
     cache: LruCache<CacheKey, (CAllocId, Placement)>,
+    evictions: u64,
 }
 
 impl Atlas {
+    /// An atlas for macroquad, requires a live GL context.
     pub fn new(max_length: u32) -> Self {
-        let mut length: i32 = 0;
-        unsafe {
-            gl::glGetIntegerv(gl::GL_MAX_TEXTURE_SIZE, &mut length);
-        }
-        let length = length.min(max_length as i32);
-        let size: Size2D<i32, UnknownUnit> = size2(length, length);
-        let length = length as u32;
+        Self::with_backend(&mut MacroquadBackend::new(), max_length)
+    }
+
+    /// An atlas whose texture is created by and drawn with `backend`.
+    pub fn with_backend(backend: &mut dyn RenderBackend, max_length: u32) -> Self {
+        let length = backend.max_texture_size().min(max_length);
+        let size: Size2D<i32, UnknownUnit> = size2(length as i32, length as i32);
         // println!("Creating a new atlas with size: {}x{}", length, length);
-        let texture = render_target(length, length).texture;
+        let texture = backend.new_texture(length, length);
         Self {
             allocator: AtlasAllocator::new(size),
             texture,
+            length,
             cache: LruCache::unbounded(),
+            evictions: 0,
         }
     }
+
+    /// Width and height of the atlas texture, in pixels
+    pub fn length(&self) -> u32 {
+        self.length
+    }
+
+    /// Number of glyphs evicted so far to make room for others
+    pub fn evictions(&self) -> u64 {
+        self.evictions
+    }
+
+    /// Fraction of the atlas taken by glyphs, gaps included
+    pub fn occupancy(&self) -> f32 {
+        let mut area = 0;
+        self.allocator
+            .for_each_allocated_rectangle(|_, rect| area += rect.area());
+        area as f32 / (self.length as f32 * self.length as f32)
+    }
 }
 
 impl Default for Atlas {
@@ -117,6 +146,7 @@ impl Atlas {
         key: CacheKey,
         cache: &mut SwashCache,
         font_system: &mut FontSystem,
+        backend: &mut dyn RenderBackend,
     ) -> Option<CAllocId> {
         if let Some((alloc_id, _)) = self.cache.get(&key) {
             return Some(*alloc_id);
@@ -134,6 +164,7 @@ impl Atlas {
         let alloc = alloc_or_evict(
             &mut self.allocator,
             &mut self.cache,
+            &mut self.evictions,
             size2(
                 width as i32 + 2 * Self::ALLOC_GAP,
                 height as i32 + 2 * Self::ALLOC_GAP,
@@ -157,12 +188,11 @@ impl Atlas {
             width: width as u16,
             height: height as u16,
         };
-        self.texture.update_part(
-            &quad_image,
+        backend.update_texture(
+            &self.texture,
             alloc.rect().min.x + Self::ALLOC_GAP,
             alloc.rect().min.y + Self::ALLOC_GAP,
-            alloc.rect().width() - 2 * Self::ALLOC_GAP,
-            alloc.rect().height() - 2 * Self::ALLOC_GAP,
+            &quad_image,
         );
 
         self.cache.push(
@@ -14,19 +14,53 @@ pub fn quad_contains(quad: [Vec2; 4], pos: Vec2) -> bool {
 }
 
 pub fn cosmic_color_to_macroquad_color(color: cosmic_text::Color) -> macroquad::color::Color {
-    macroquad::color::Color {
-        r: (color.r() as f32 / 255.0),
-        g: (color.g() as f32 / 255.0),
-        b: (color.b() as f32 / 255.0),
-        a: (color.a() as f32 / 255.0),
-    }
+    crate::color::from_rgba8(color.as_rgba())
 }
 
 pub fn macroquad_color_to_cosmic_color(color: macroquad::color::Color) -> cosmic_text::Color {
-    cosmic_text::Color::rgba(
-        (color.g * 255.0) as u8,
-        (color.b * 255.0) as u8,
-        (color.r * 255.0) as u8,
-        (color.a * 255.0) as u8,
-    )
+    let [r, g, b, a] = crate::color::to_rgba8(color);
+    cosmic_text::Color::rgba(r, g, b, a)
+}
+
+/// Writes an RGBA [`Image`](macroquad::texture::Image) to `path` as PNG.
+///
+/// Rows are expected top to bottom.
+pub fn save_png(
+    image: &macroquad::texture::Image,
+    path: impl AsRef<std::path::Path>,
+) -> anyhow::Result<()> {
+    let file = std::io::BufWriter::new(std::fs::File::create(path)?);
+    let mut encoder = png::Encoder::new(file, image.width as u32, image.height as u32);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(&image.bytes)?;
+    Ok(())
+}
+
+/// Reads a PNG file into an RGBA [`Image`](macroquad::texture::Image), rows top to bottom.
+pub fn load_png(path: impl AsRef<std::path::Path>) -> anyhow::Result<macroquad::texture::Image> {
+    let mut decoder = png::Decoder::new(std::io::BufReader::new(std::fs::File::open(path)?));
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info()?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf)?;
+    buf.truncate(info.buffer_size());
+    let bytes = match info.color_type {
+        png::ColorType::Rgba => buf,
+        png::ColorType::Rgb => buf
+            .chunks_exact(3)
+            .flat_map(|p| [p[0], p[1], p[2], 255])
+            .collect(),
+        png::ColorType::GrayscaleAlpha => buf
+            .chunks_exact(2)
+            .flat_map(|p| [p[0], p[0], p[0], p[1]])
+            .collect(),
+        png::ColorType::Grayscale => buf.iter().flat_map(|g| [*g, *g, *g, 255]).collect(),
+        png::ColorType::Indexed => anyhow::bail!("indexed PNGs should have been expanded"),
+    };
+    Ok(macroquad::texture::Image {
+        bytes,
+        width: info.width as u16,
+        height: info.height as u16,
+    })
 }
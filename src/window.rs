@@ -1,29 +1,49 @@
+use std::{any::Any, f32::consts::FRAC_PI_2, time::Instant};
+
 use lyon::{
-    path::{Path, PathEvent},
+    algorithms::aabb::bounding_box,
+    geom::Arc,
+    math::{Angle, Box2D, Vector},
+    path::builder::BorderRadii,
+    path::{Path, PathEvent, Polygon, Winding},
     tessellation::{
-        BuffersBuilder, FillOptions, FillTessellator, StrokeOptions, StrokeTessellator,
+        BuffersBuilder, FillOptions, FillTessellator, FillVertex, StrokeTessellator, StrokeVertex,
         VertexBuffers,
     },
 };
 use macroquad::{
-    camera::{Camera2D, set_camera},
-    math::vec2,
-    prelude::DrawMode,
-    texture::Texture2D,
+    camera::Camera2D,
+    color::{Color, WHITE},
+    math::{Rect, Vec2, vec2},
+    texture::{Image, Texture2D},
     ui::Vertex,
-    window::{get_internal_gl, screen_dpi_scale, screen_height, screen_width},
 };
+use tracing::warn;
 
 use crate::{
-    shading::{ShadedConstructor, Shading},
+    backdrop::BackdropBlur,
+    backend::{MacroquadBackend, RenderBackend},
+    batch::{Batcher, DrawState, split_mesh, submission_limits},
+    clip::Clip,
+    component::Component,
+    image::NineSlice,
+    layer::LayerOptions,
+    retained::{DisplayCommand, DisplayList},
+    shading::{BlendMode, IntoShading, ScreenTextureShading, ShadedConstructor, Shading, Wrap},
+    shadow::{Shadow, box_shadow_mesh},
+    stats::FrameStats,
+    stroke::StrokeStyle,
+    tessellation::{Geometry, TessellationCache, add_fringe, fill_key, stroke_key},
     text::Atlas,
-    utils::Point,
+    utils::{Point, Transform, save_png},
 };
+use nalgebra::Vector2;
 
 #[must_use = "Call `commit` to do the actual drawing"]
 pub struct VertexBuilder<S> {
     vertices: Vec<Vertex>,
-    indices: Vec<u16>,
+    indices: Vec<u32>,
+    blend: BlendMode,
     shading: S,
 }
 
@@ -32,87 +52,379 @@ impl<S: Shading> VertexBuilder<S> {
         Self {
             vertices: Vec::new(),
             indices: Vec::new(),
+            blend: shading.blend_mode(),
             shading,
         }
     }
 
+    /// Overrides the blend mode of the shading for this draw.
+    pub fn blend(mut self, blend: BlendMode) -> Self {
+        self.blend = blend;
+        self
+    }
+
+    /// Number of vertices added so far, which is also the index of the next one.
+    pub fn len(&self) -> u32 {
+        self.vertices.len() as u32
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vertices.is_empty()
+    }
+
     pub fn add(mut self, x: f32, y: f32, alpha: f32) -> Self {
         self.vertices
             .push(self.shading.new_vertex(&Point::new(x, y), alpha));
         self
     }
 
-    pub fn triangle(mut self, a: u16, b: u16, c: u16) -> Self {
+    /// Like [`Self::add`], with texture coordinates for the texture of the shading.
+    pub fn add_textured(mut self, x: f32, y: f32, u: f32, v: f32, alpha: f32) -> Self {
+        let mut vertex = self.shading.new_vertex(&Point::new(x, y), alpha);
+        vertex.uv = vec2(u, v);
+        self.vertices.push(vertex);
+        self
+    }
+
+    pub fn triangle(mut self, a: u32, b: u32, c: u32) -> Self {
         self.indices.push(a);
         self.indices.push(b);
         self.indices.push(c);
         self
     }
 
-    pub fn commit(self) {
-        let gl = unsafe { get_internal_gl() }.quad_gl;
-        gl.texture(self.shading.texture().as_ref());
-        gl.draw_mode(DrawMode::Triangles);
-        gl.geometry(&self.vertices, &self.indices);
+    /// Meshes too large for a single submission are split into several,
+    /// triangles referring to vertices that don't exist are skipped.
+    pub fn commit(self, target: &mut Window) {
+        let state = DrawState {
+            blend: self.blend,
+            ..DrawState::of(&self.shading)
+        };
+        let (max_vertices, max_indices) = target.max_submission_size();
+        if self.vertices.len() <= max_vertices && self.indices.len() <= max_indices {
+            let mut indices = Vec::with_capacity(self.indices.len());
+            for triangle in self.indices.chunks_exact(3) {
+                if triangle.iter().all(|&i| (i as usize) < self.vertices.len()) {
+                    indices.extend(triangle.iter().map(|&i| i as u16));
+                }
+            }
+            target.submit(&state, &self.vertices, &indices);
+            return;
+        }
+        split_mesh(
+            &self.vertices,
+            &self.indices,
+            (max_vertices, max_indices),
+            |vertices, indices| target.submit(&state, vertices, indices),
+        );
     }
 }
 
-pub struct Window {
-    pub pixel_width: u32,
-    pub pixel_height: u32,
+struct ClipEntry {
+    /// The scissor before this clip was pushed
+    previous_scissor: Option<Rect>,
+    /// Geometry pushed to the stencil, if the clip could not be done with the scissor alone
+    stencil: Option<(Vec<Vertex>, Vec<u16>)>,
+}
 
-    /// No need to care about physical PPI
-    pub logical_ppi: f32,
+/// Size and DPI scale of the screen, see [`Window`] for the coordinate model.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ScreenMetrics {
+    /// In logical pixels
+    pub size: (f32, f32),
+    /// Physical pixels per logical pixel
+    pub dpi_scale: f32,
+}
+
+impl ScreenMetrics {
+    pub fn physical_size(&self) -> (u32, u32) {
+        (
+            (self.size.0 * self.dpi_scale).round() as u32,
+            (self.size.1 * self.dpi_scale).round() as u32,
+        )
+    }
+
+    pub fn to_physical(&self, logical: Vec2) -> Vec2 {
+        logical * self.dpi_scale
+    }
+
+    /// For positions of mouse and touch events, which are physical.
+    pub fn to_logical(&self, physical: Vec2) -> Vec2 {
+        physical / self.dpi_scale
+    }
+}
+
+/// Where everything is drawn.
+///
+/// # Coordinates
+///
+/// All coordinates and sizes, touches included, are in logical pixels: the origin is the
+/// top-left corner of the screen, y points down, and one logical pixel is
+/// [`Self::dpi_scale`] physical pixels, so that things look the same size on every screen.
+/// Only tessellation tolerances, glyph rasterization and captured images deal in
+/// physical pixels.
+///
+/// Note that macroquad's `mouse_position` and `screen_width` are logical,
+/// while positions in its input events are physical.
+pub struct Window {
+    metrics: ScreenMetrics,
 
     vertex_buffers: VertexBuffers<Vertex, u16>,
     fill_tessellator: FillTessellator,
     fill_options: FillOptions,
     stroke_tessellator: StrokeTessellator,
-    stroke_options: StrokeOptions,
+    /// Maximum distance between a curve and its tessellation, in physical pixels
+    tolerance: f32,
+    tessellation_cache: TessellationCache,
+    anti_alias: bool,
 
     pub(crate) font_system: cosmic_text::FontSystem,
     pub(crate) swash_cache: cosmic_text::SwashCache,
     pub(crate) font_atlas: Atlas,
+
+    batcher: Batcher,
+    /// Of the frame being drawn
+    stats: FrameStats,
+    last_stats: FrameStats,
+    frame_start: Instant,
+    clip_stack: Vec<ClipEntry>,
+    /// Display lists being recorded, innermost last, with the atlas evictions when they began
+    recordings: Vec<(DisplayList, u64)>,
+    scissor: Option<Rect>,
+    pub(crate) backend: Box<dyn RenderBackend>,
 }
 
 impl Default for Window {
     fn default() -> Self {
+        Self::new(MacroquadBackend::new())
+    }
+}
+
+impl Window {
+    const DEFAULT_TOLERANCE: f32 = 0.3;
+
+    pub fn new(backend: impl RenderBackend) -> Self {
+        let mut backend: Box<dyn RenderBackend> = Box::new(backend);
+        let metrics = ScreenMetrics {
+            size: backend.screen_size(),
+            dpi_scale: backend.dpi_scale(),
+        };
         Self {
-            pixel_width: screen_width() as u32,
-            pixel_height: screen_height() as u32,
-            logical_ppi: screen_dpi_scale(),
+            metrics,
 
             vertex_buffers: VertexBuffers::new(),
             fill_tessellator: FillTessellator::new(),
             fill_options: FillOptions::tolerance(Self::DEFAULT_TOLERANCE),
             stroke_tessellator: StrokeTessellator::new(),
-            stroke_options: StrokeOptions::default(),
+            tolerance: Self::DEFAULT_TOLERANCE,
+            tessellation_cache: TessellationCache::default(),
+            anti_alias: false,
             font_system: cosmic_text::FontSystem::new(),
             swash_cache: cosmic_text::SwashCache::new(),
-            font_atlas: Atlas::new(3_000),
+            font_atlas: Atlas::with_backend(&mut *backend, 3_000),
+            batcher: Batcher::default(),
+            stats: FrameStats::default(),
+            last_stats: FrameStats::default(),
+            frame_start: Instant::now(),
+            clip_stack: Vec::new(),
+            recordings: Vec::new(),
+            scissor: None,
+            backend,
         }
     }
-}
 
-impl Window {
-    const DEFAULT_TOLERANCE: f32 = 0.3;
+    pub fn metrics(&self) -> ScreenMetrics {
+        self.metrics
+    }
+
+    /// Size of the screen in logical pixels.
+    pub fn logical_size(&self) -> (f32, f32) {
+        self.metrics.size
+    }
+
+    pub fn physical_size(&self) -> (u32, u32) {
+        self.metrics.physical_size()
+    }
+
+    /// Physical pixels per logical pixel.
+    pub fn dpi_scale(&self) -> f32 {
+        self.metrics.dpi_scale
+    }
+
+    pub fn to_physical(&self, logical: Vec2) -> Vec2 {
+        self.metrics.to_physical(logical)
+    }
+
+    /// For positions of mouse and touch events, which are physical.
+    pub fn to_logical(&self, physical: Vec2) -> Vec2 {
+        self.metrics.to_logical(physical)
+    }
+
+    /// The backend this window draws with, if it is a `B`.
+    pub fn backend<B: RenderBackend>(&self) -> Option<&B> {
+        (&*self.backend as &dyn Any).downcast_ref()
+    }
+
+    /// The backend this window draws with, if it is a `B`.
+    pub fn backend_mut<B: RenderBackend>(&mut self) -> Option<&mut B> {
+        (&mut *self.backend as &mut dyn Any).downcast_mut()
+    }
+
+    /// Fonts labels are shaped with, for loading fonts other than the system ones.
+    /// Labels that are already laid out keep their layout.
+    pub fn font_system_mut(&mut self) -> &mut cosmic_text::FontSystem {
+        &mut self.font_system
+    }
+
+    /// Tessellations reused by [`Self::fill_local_path`] and [`Self::stroke_local_path`].
+    pub fn tessellation_cache(&self) -> &TessellationCache {
+        &self.tessellation_cache
+    }
+
+    pub fn tessellation_cache_mut(&mut self) -> &mut TessellationCache {
+        &mut self.tessellation_cache
+    }
+
+    pub fn fill_options(&self) -> FillOptions {
+        self.fill_options
+    }
+
+    /// The tolerance is managed by the window, see [`Self::set_tolerance`].
+    pub fn set_fill_options(&mut self, f: impl FnOnce(FillOptions) -> FillOptions) {
+        self.fill_options = f(self.fill_options)
+    }
+
+    /// Sets how far tessellated curves may be off, in physical pixels.
+    pub fn set_tolerance(&mut self, tolerance: f32) {
+        self.tolerance = tolerance;
+    }
+
+    /// Whether fills get a feathered edge, see [`Self::set_anti_alias`].
+    pub fn anti_alias(&self) -> bool {
+        self.anti_alias
+    }
+
+    /// Makes [`Self::fill_path`] and [`Self::fill_local_path`] fade out the edges of what
+    /// they draw over one physical pixel, for smooth shapes without MSAA.
+    ///
+    /// Shapes grow by half a pixel, and overlapping edges of translucent fills show.
+    pub fn set_anti_alias(&mut self, anti_alias: bool) {
+        self.anti_alias = anti_alias;
+    }
+
+    /// Runs `f` with anti-aliasing turned on or off, see [`Self::set_anti_alias`].
+    pub fn with_anti_alias<R>(&mut self, anti_alias: bool, f: impl FnOnce(&mut Self) -> R) -> R {
+        let previous = std::mem::replace(&mut self.anti_alias, anti_alias);
+        let result = f(self);
+        self.anti_alias = previous;
+        result
+    }
+
+    /// Tolerance for paths in the coordinate system of `tr`.
+    ///
+    /// The scale is rounded up to a power of two, so that a path drawn at slightly
+    /// different scales can still reuse its cached tessellation.
+    fn tolerance_for(&self, tr: &Transform) -> f32 {
+        let scale = transform_scale(tr) * self.metrics.dpi_scale;
+        let scale = scale.log2().ceil().exp2();
+        if scale.is_normal() {
+            self.tolerance / scale
+        } else {
+            self.tolerance
+        }
+    }
+
+    /// Queues geometry for drawing. Consecutive submissions with the same `state` are
+    /// merged into one backend draw call, see [`Self::flush`].
+    pub(crate) fn submit(&mut self, state: &DrawState, vertices: &[Vertex], indices: &[u16]) {
+        self.record(|| DisplayCommand::Draw {
+            state: state.clone(),
+            vertices: vertices.to_vec(),
+            indices: indices.to_vec(),
+        });
+        self.queue(state, vertices, indices);
+    }
+
+    /// Like [`Self::submit`], but without recording.
+    fn queue(&mut self, state: &DrawState, vertices: &[Vertex], indices: &[u16]) {
+        self.batcher.push(
+            &mut *self.backend,
+            &mut self.stats,
+            state,
+            vertices,
+            indices,
+        );
+    }
 
-    pub fn set_stroke_options(&mut self, f: impl FnOnce(StrokeOptions) -> StrokeOptions) {
-        self.stroke_options = f(self.stroke_options)
+    /// See [`submission_limits`].
+    fn max_submission_size(&self) -> (usize, usize) {
+        submission_limits(&*self.backend)
+    }
+
+    /// Sends all queued geometry to the backend.
+    ///
+    /// This happens automatically when the draw state changes, but has to be called
+    /// once at the end of every frame, before presenting it.
+    pub fn flush(&mut self) {
+        self.batcher.flush(&mut *self.backend, &mut self.stats);
     }
 
-    fn set_tolerance(&mut self, tol: f32) {
-        self.fill_options.tolerance = tol / (self.pixel_width as f32);
+    fn emit_lyon(&mut self, state: &DrawState) {
+        if !self.recordings.is_empty() {
+            let command = DisplayCommand::Draw {
+                state: state.clone(),
+                vertices: self.vertex_buffers.vertices.clone(),
+                indices: self.vertex_buffers.indices.clone(),
+            };
+            self.record(|| command);
+        }
+        self.batcher.push(
+            &mut *self.backend,
+            &mut self.stats,
+            state,
+            &self.vertex_buffers.vertices,
+            &self.vertex_buffers.indices,
+        );
+        self.vertex_buffers.vertices.clear();
+        self.vertex_buffers.indices.clear();
     }
 
-    fn emit_lyon(&mut self, texture: Option<Texture2D>) {
-        let gl = unsafe { get_internal_gl() }.quad_gl;
-        gl.texture(texture.as_ref());
-        gl.draw_mode(DrawMode::Triangles);
-        gl.geometry(
-            &std::mem::take(&mut self.vertex_buffers.vertices),
-            &std::mem::take(&mut self.vertex_buffers.indices),
+    /// Caches the glyph in the font atlas if needed, returning its rectangle in the atlas
+    /// (in pixels) and its placement.
+    pub(crate) fn cache_glyph(
+        &mut self,
+        key: cosmic_text::CacheKey,
+    ) -> Option<(Rect, cosmic_text::Placement)> {
+        if self.font_atlas.get_glyph(key).is_some() {
+            self.stats.glyph_hits += 1;
+        } else {
+            self.stats.glyph_misses += 1;
+            if self.batcher.state().texture.as_ref() == Some(&self.font_atlas.texture) {
+                // Queued glyphs must be drawn before the atlas changes under them
+                self.flush();
+            }
+        }
+        let evictions = self.font_atlas.evictions();
+        let cached = self.font_atlas.cache_glyph(
+            key,
+            &mut self.swash_cache,
+            &mut self.font_system,
+            &mut *self.backend,
         );
+        self.stats.glyph_evictions += (self.font_atlas.evictions() - evictions) as u32;
+        cached?;
+        Some((
+            self.font_atlas.get_glyph(key)?,
+            self.font_atlas.get_placement(key)?,
+        ))
+    }
+
+    /// Runs `f`, counting the time it takes as tessellation time.
+    fn timed<R>(&mut self, f: impl FnOnce(&mut Self) -> R) -> R {
+        let start = Instant::now();
+        let result = f(self);
+        self.stats.tessellation_time += start.elapsed();
+        result
     }
 
     /// `f` does the actual drawing
@@ -122,18 +434,451 @@ impl Window {
         alpha: f32,
         f: impl FnOnce(&mut Self, ShadedConstructor<S>),
     ) {
-        self.set_tolerance(Self::DEFAULT_TOLERANCE);
-        let tex = shading.texture();
-        f(self, ShadedConstructor { shading, alpha });
-        self.emit_lyon(tex);
+        let tolerance = self.tolerance_for(&Transform::identity());
+        self.fill_options.tolerance = tolerance;
+        let state = DrawState::of(&shading);
+        self.timed(|this| f(this, ShadedConstructor { shading, alpha }));
+        self.emit_lyon(&state);
     }
 
-    pub fn set_camera(&self) {
-        set_camera(&Camera2D {
-            zoom: vec2(
-                self.pixel_width as f32 / 2.0,
-                -(self.pixel_height as f32 / 2.0),
-            ),
+    /// Restricts all following drawing to `clip` (in the coordinate system of `tr`),
+    /// intersected with the clips already pushed. Undo with [`Self::pop_clip`].
+    ///
+    /// Rectangles that stay axis-aligned under `tr` only need a scissor, anything else
+    /// also goes through the stencil buffer.
+    pub fn push_clip(&mut self, clip: &Clip, tr: &Transform) {
+        self.record(|| DisplayCommand::PushClip(clip.clone(), *tr));
+        self.flush();
+        let axis_aligned =
+            tr[(0, 1)] == 0.0 && tr[(1, 0)] == 0.0 && tr[(2, 0)] == 0.0 && tr[(2, 1)] == 0.0;
+        let (bounds, stencil) = match clip {
+            Clip::Rect(rect) if axis_aligned => {
+                let min = tr.transform_point(&Point::new(rect.min.x, rect.min.y));
+                let max = tr.transform_point(&Point::new(rect.max.x, rect.max.y));
+                let (x0, x1) = (min.x.min(max.x), min.x.max(max.x));
+                let (y0, y1) = (min.y.min(max.y), min.y.max(max.y));
+                (Rect::new(x0, y0, x1 - x0, y1 - y0), None)
+            }
+            _ => {
+                let Some((vertices, indices)) = self.tessellate_clip(clip, tr) else {
+                    warn!("Drawing without the clip");
+                    // Still balance the stack
+                    self.clip_stack.push(ClipEntry {
+                        previous_scissor: self.scissor,
+                        stencil: None,
+                    });
+                    return;
+                };
+                if vertices.is_empty() {
+                    // Nothing is inside, which the scissor alone takes care of
+                    (Rect::new(0.0, 0.0, 0.0, 0.0), None)
+                } else {
+                    let (mut min, mut max) = (vec2(f32::MAX, f32::MAX), vec2(f32::MIN, f32::MIN));
+                    for v in &vertices {
+                        min = min.min(v.position.truncate());
+                        max = max.max(v.position.truncate());
+                    }
+                    let bounds = Rect::new(min.x, min.y, max.x - min.x, max.y - min.y);
+                    (bounds, Some((vertices, indices)))
+                }
+            }
+        };
+        let previous_scissor = self.scissor;
+        let scissor = match previous_scissor {
+            Some(previous) => previous
+                .intersect(bounds)
+                .unwrap_or(Rect::new(bounds.x, bounds.y, 0.0, 0.0)),
+            None => bounds,
+        };
+        self.scissor = Some(scissor);
+        self.backend.set_scissor(Some(scissor));
+        if let Some((vertices, indices)) = &stencil {
+            self.backend.push_stencil(vertices, indices);
+        }
+        self.clip_stack.push(ClipEntry {
+            previous_scissor,
+            stencil,
+        });
+    }
+
+    /// Undoes the last [`Self::push_clip`].
+    pub fn pop_clip(&mut self) {
+        let Some(entry) = self.clip_stack.pop() else {
+            return;
+        };
+        self.record(|| DisplayCommand::PopClip);
+        self.flush();
+        if let Some((vertices, indices)) = &entry.stencil {
+            self.backend.pop_stencil(vertices, indices);
+        }
+        if self.scissor != entry.previous_scissor {
+            self.scissor = entry.previous_scissor;
+            self.backend.set_scissor(self.scissor);
+        }
+    }
+
+    /// The clip as triangles in the final coordinate system.
+    fn tessellate_clip(&mut self, clip: &Clip, tr: &Transform) -> Option<(Vec<Vertex>, Vec<u16>)> {
+        let to_vertex = |x: f32, y: f32| {
+            let p = tr.transform_point(&Point::new(x, y));
+            Vertex::new(p.x, p.y, 0., 0., 0., Color::new(1., 1., 1., 1.))
+        };
+        match clip {
+            Clip::Rect(rect) => Some((
+                vec![
+                    to_vertex(rect.min.x, rect.min.y),
+                    to_vertex(rect.max.x, rect.min.y),
+                    to_vertex(rect.max.x, rect.max.y),
+                    to_vertex(rect.min.x, rect.max.y),
+                ],
+                vec![0, 1, 2, 0, 2, 3],
+            )),
+            Clip::Path(path) => {
+                let mut buffers: VertexBuffers<Vertex, u16> = VertexBuffers::new();
+                let options = FillOptions::tolerance(self.tolerance_for(tr));
+                self.timed(|this| {
+                    this.fill_tessellator.tessellate_path(
+                        path,
+                        &options,
+                        &mut BuffersBuilder::new(&mut buffers, |v: FillVertex| {
+                            to_vertex(v.position().x, v.position().y)
+                        }),
+                    )
+                })
+                .inspect_err(|e| warn!("Failed to tessellate clip path: {:?}", e))
+                .ok()?;
+                Some((buffers.vertices, buffers.indices))
+            }
+        }
+    }
+
+    /// Draws everything until the matching [`Self::pop_layer`] into an offscreen layer.
+    ///
+    /// Layers are pooled by the backend, so pushing one every frame is fine.
+    pub fn push_layer(&mut self) {
+        self.record(|| DisplayCommand::PushLayer);
+        self.flush();
+        self.backend.push_layer();
+    }
+
+    /// Ends the last [`Self::push_layer`], compositing the layer as described by `options`.
+    ///
+    /// `options.transform` is in the final coordinate system.
+    pub fn pop_layer(&mut self, options: &LayerOptions) {
+        self.record(|| DisplayCommand::PopLayer(options.clone()));
+        self.flush();
+        let texture = self.backend.pop_layer();
+        let (width, height) = self.backend.screen_size();
+        let alpha = options.alpha.clamp(0.0, 1.0);
+        // Layers are premultiplied, so is the color they are tinted with
+        let color = Color::new(alpha, alpha, alpha, alpha);
+        let vertices = [(0., 0.), (1., 0.), (1., 1.), (0., 1.)].map(|(u, v)| {
+            let p = options
+                .transform
+                .transform_point(&Point::new(u * width, v * height));
+            Vertex::new(p.x, p.y, 0., u, v, color)
+        });
+        self.queue(
+            &DrawState {
+                texture: Some(texture),
+                blend: options.blend,
+                premultiplied: true,
+                material: None,
+                gradient: None,
+                wrap: Wrap::Clamp,
+            },
+            &vertices,
+            &[0, 1, 2, 0, 2, 3],
+        );
+    }
+
+    /// Runs `f` with everything it draws going to an offscreen layer,
+    /// see [`Self::push_layer`] and [`Self::pop_layer`].
+    pub fn with_layer<R>(&mut self, options: &LayerOptions, f: impl FnOnce(&mut Self) -> R) -> R {
+        self.push_layer();
+        let result = f(self);
+        self.pop_layer(options);
+        result
+    }
+
+    /// What has been drawn so far to the current target, the screen or the innermost layer,
+    /// in physical pixels with rows top to bottom.
+    ///
+    /// `region` (logical pixels) crops the image, `None` captures everything.
+    /// This waits for the GPU, so it is not meant for every frame.
+    pub fn capture(&mut self, region: Option<Rect>) -> Image {
+        self.flush();
+        let (width, height) = self.backend.screen_size();
+        let screen = Rect::new(0.0, 0.0, width, height);
+        let Some(region) = region.unwrap_or(screen).intersect(screen) else {
+            return Image::empty();
+        };
+        let texture = self.backend.snapshot(region);
+        let image = self.backend.read_texture(&texture);
+        // Physical pixels per logical pixel, as the texture actually is
+        let scale = image.width as f32 / width;
+        let x0 = ((region.x * scale).floor() as usize).min(image.width as usize);
+        let y0 = ((region.y * scale).floor() as usize).min(image.height as usize);
+        let x1 = ((region.right() * scale).ceil() as usize).clamp(x0, image.width as usize);
+        let y1 = ((region.bottom() * scale).ceil() as usize).clamp(y0, image.height as usize);
+        let mut bytes = Vec::with_capacity((x1 - x0) * (y1 - y0) * 4);
+        for row in image
+            .bytes
+            .chunks_exact(image.width as usize * 4)
+            .take(y1)
+            .skip(y0)
+        {
+            for pixel in row[x0 * 4..x1 * 4].chunks_exact(4) {
+                // Snapshots are premultiplied
+                let alpha = pixel[3] as u32;
+                let unpremultiply = |c: u8| {
+                    (c as u32 * 255)
+                        .checked_div(alpha)
+                        .map_or(0, |c| c.min(255) as u8)
+                };
+                bytes.extend([
+                    unpremultiply(pixel[0]),
+                    unpremultiply(pixel[1]),
+                    unpremultiply(pixel[2]),
+                    pixel[3],
+                ]);
+            }
+        }
+        Image {
+            bytes,
+            width: (x1 - x0) as u16,
+            height: (y1 - y0) as u16,
+        }
+    }
+
+    /// Renders `component` on its own, into an offscreen layer instead of the current target,
+    /// and captures it like [`Self::capture`]. Where it draws nothing is transparent.
+    pub fn capture_component(
+        &mut self,
+        component: &mut dyn Component,
+        tr: &Transform,
+        region: Option<Rect>,
+    ) -> Image {
+        self.invalidate_recordings();
+        // The layer is never composited, so nothing drawn into it is recorded
+        let recordings = std::mem::take(&mut self.recordings);
+        self.flush();
+        self.backend.push_layer();
+        component.render(tr, self);
+        let image = self.capture(region);
+        self.backend.pop_layer();
+        self.recordings = recordings;
+        image
+    }
+
+    /// Saves the current frame as it is drawn so far to a PNG file, see [`Self::capture`].
+    pub fn save_screenshot(&mut self, path: impl AsRef<std::path::Path>) -> anyhow::Result<()> {
+        save_png(&self.capture(None), path)
+    }
+
+    /// Draws the shadow of `path`, which is in the coordinate system of `tr`, in `color`.
+    ///
+    /// The path is drawn offscreen and blurred, [`Self::draw_box_shadow`] is a lot cheaper
+    /// for rounded rectangles and takes any shading. A negative spread counts as none here.
+    pub fn draw_shadow(&mut self, path: &Path, tr: &Transform, shadow: &Shadow, color: Color) {
+        // The mask is drawn on its own, not recorded
+        self.invalidate_recordings();
+        let shifted =
+            Transform::new_translation(&Vector2::new(shadow.offset.x, shadow.offset.y)) * tr;
+        let extent = shadow.extent();
+        let bounds = self.screen_bounds(path, &shifted);
+        let Some(region) = self.visible(Rect::new(
+            bounds.x - extent,
+            bounds.y - extent,
+            bounds.w + extent * 2.0,
+            bounds.h + extent * 2.0,
+        )) else {
+            return;
+        };
+
+        self.push_layer();
+        self.fill_local_path(path, &shifted, WHITE.into_shading(), 1.0);
+        if shadow.spread > 0.0 {
+            let thickness = shadow.spread * 2.0 / transform_scale(tr);
+            self.stroke_local_path(path, &shifted, WHITE.into_shading(), 1.0, thickness);
+        }
+        self.flush();
+        let mut mask = self.backend.pop_layer();
+        if shadow.blur > 0.0 {
+            let downsample = self.blur_downsample(shadow.blur);
+            mask = self.backend.blur(&mask, region, shadow.blur, downsample);
+        }
+        // The texture coordinates are taken by the mask
+        self.draw_screen_texture(&mask, region, &color.into_shading());
+    }
+
+    /// Draws the shadow of a rounded rectangle, which is in the coordinate system of `tr`,
+    /// colored by `shading`.
+    ///
+    /// Unlike [`Self::draw_shadow`] this needs no offscreen drawing, at the price
+    /// of a linear falloff instead of a gaussian one.
+    pub fn draw_box_shadow(
+        &mut self,
+        rect: &Box2D,
+        radii: &BorderRadii,
+        tr: &Transform,
+        shadow: &Shadow,
+        shading: impl Shading,
+    ) {
+        let scale = transform_scale(tr);
+        let largest_radius = [
+            radii.top_left,
+            radii.top_right,
+            radii.bottom_left,
+            radii.bottom_right,
+        ]
+        .into_iter()
+        .fold(0.0f32, f32::max);
+        // In logical pixels
+        let radius = largest_radius * scale + shadow.spread + shadow.blur;
+        let tolerance = self.tolerance / self.metrics.dpi_scale;
+        let segments = if radius > tolerance {
+            (FRAC_PI_2 / (2.0 * (1.0 - tolerance / radius).acos())).ceil() as usize
+        } else {
+            1
+        };
+        let (vertices, indices) = box_shadow_mesh(
+            rect,
+            radii,
+            shadow.spread / scale,
+            shadow.blur / scale,
+            segments.clamp(1, 32),
+        );
+        let mut builder = self.vertex_builder(shading);
+        for (p, alpha) in vertices {
+            let p = tr.transform_point(&Point::new(p.x, p.y));
+            builder = builder.add(p.x + shadow.offset.x, p.y + shadow.offset.y, alpha);
+        }
+        for triangle in indices.chunks_exact(3) {
+            builder = builder.triangle(triangle[0], triangle[1], triangle[2]);
+        }
+        builder.commit(self);
+    }
+
+    /// Fills `path`, which is in the coordinate system of `tr`, with a blurred copy of what
+    /// has been drawn behind it so far, then with `tint` on top: frosted glass.
+    ///
+    /// The blurred copy is blended over the original, so a translucent backdrop still
+    /// shows through. Inside a layer, only what has been drawn to the layer is seen.
+    pub fn fill_backdrop_blur(
+        &mut self,
+        path: &Path,
+        tr: &Transform,
+        blur: &BackdropBlur,
+        tint: impl Shading,
+    ) {
+        let bounds = self.screen_bounds(path, tr);
+        let Some(region) = self.visible(bounds) else {
+            return;
+        };
+        // The blur reaches this far out for its samples
+        let margin = blur.radius * 1.5 + blur.downsample as f32 / self.metrics.dpi_scale;
+        let Some(outer) = self.visible(Rect::new(
+            region.x - margin,
+            region.y - margin,
+            region.w + margin * 2.0,
+            region.h + margin * 2.0,
+        )) else {
+            return;
+        };
+        // What is below changes without the recording knowing
+        self.invalidate_recordings();
+        self.flush();
+        let mut backdrop = self.backend.snapshot(outer);
+        if blur.radius > 0.0 {
+            backdrop = self
+                .backend
+                .blur(&backdrop, region, blur.radius, blur.downsample);
+        }
+        let shading = ScreenTextureShading {
+            texture: backdrop,
+            screen_size: self.backend.screen_size(),
+        };
+        self.fill_local_path(path, tr, shading, 1.0);
+        self.fill_local_path(path, tr, tint, 1.0);
+    }
+
+    /// Bounding box of `path` in the final coordinate system.
+    fn screen_bounds(&self, path: &Path, tr: &Transform) -> Rect {
+        let bounds = bounding_box(path.iter());
+        let corners = [
+            (bounds.min.x, bounds.min.y),
+            (bounds.max.x, bounds.min.y),
+            (bounds.max.x, bounds.max.y),
+            (bounds.min.x, bounds.max.y),
+        ]
+        .map(|(x, y)| {
+            let p = tr.transform_point(&Point::new(x, y));
+            vec2(p.x, p.y)
+        });
+        let (mut min, mut max) = (corners[0], corners[0]);
+        for corner in corners {
+            min = min.min(corner);
+            max = max.max(corner);
+        }
+        Rect::new(min.x, min.y, max.x - min.x, max.y - min.y)
+    }
+
+    /// The part of `rect` that is on the screen, if any.
+    fn visible(&self, rect: Rect) -> Option<Rect> {
+        let (width, height) = self.backend.screen_size();
+        rect.intersect(Rect::new(0.0, 0.0, width, height))
+    }
+
+    /// Downsampling factor for a blur of `radius` logical pixels.
+    fn blur_downsample(&self, radius: f32) -> u32 {
+        match radius * self.metrics.dpi_scale {
+            r if r < 8.0 => 1,
+            r if r < 24.0 => 2,
+            _ => 4,
+        }
+    }
+
+    /// Draws the `region` (logical pixels) of a premultiplied texture covering the screen,
+    /// such as a layer, tinted by `shading`.
+    fn draw_screen_texture(&mut self, texture: &Texture2D, region: Rect, shading: &impl Shading) {
+        let (width, height) = self.backend.screen_size();
+        let blend = shading.blend_mode();
+        let vertices = [
+            (region.x, region.y),
+            (region.right(), region.y),
+            (region.right(), region.bottom()),
+            (region.x, region.bottom()),
+        ]
+        .map(|(x, y)| {
+            let mut vertex = shading.new_vertex(&Point::new(x, y), 1.0);
+            if blend != BlendMode::Premultiplied {
+                let alpha = vertex.color[3] as u16;
+                for channel in &mut vertex.color[..3] {
+                    *channel = (*channel as u16 * alpha / 255) as u8;
+                }
+            }
+            vertex.uv = vec2(x / width, y / height);
+            vertex
+        });
+        self.submit(
+            &DrawState {
+                texture: Some(texture.clone()),
+                blend,
+                premultiplied: true,
+                material: None,
+                gradient: None,
+                wrap: Wrap::Clamp,
+            },
+            &vertices,
+            &[0, 1, 2, 0, 2, 3],
+        );
+    }
+
+    pub fn set_camera(&mut self) {
+        self.flush();
+        self.backend.set_camera(&Camera2D {
+            zoom: vec2(self.metrics.size.0 / 2.0, -(self.metrics.size.1 / 2.0)),
             viewport: None,
             ..Default::default()
         });
@@ -148,25 +893,206 @@ impl Window {
     ///   - a, c, d
     ///
     ///  quad: [a, b, c, d]
-    pub fn fill_quad(&self, quad: [Point; 4], shading: impl Shading) {
+    pub fn fill_quad(&mut self, quad: [Point; 4], shading: impl Shading, alpha: f32) {
         let [a, b, c, d] = quad;
-        // TODO: how to set alpha here????
         self.vertex_builder(shading)
-            .add(a.x, a.y, 1.0)
-            .add(b.x, b.y, 1.0)
-            .add(c.x, c.y, 1.0)
-            .add(d.x, d.y, 1.0)
+            .add(a.x, a.y, alpha)
+            .add(b.x, b.y, alpha)
+            .add(c.x, c.y, alpha)
+            .add(d.x, d.y, alpha)
             .triangle(0, 1, 2)
             .triangle(0, 2, 3)
-            .commit();
+            .commit(self);
+    }
+
+    /// Draws the `uv` part of `texture` (in normalized texture coordinates) to the `dest`
+    /// rectangle, which is in the final coordinate system.
+    pub(crate) fn draw_texture_rect(
+        &mut self,
+        texture: &Texture2D,
+        dest: Rect,
+        uv: Rect,
+        color: Color,
+    ) {
+        let vertices = [
+            Vertex::new(dest.x, dest.y, 0., uv.x, uv.y, color),
+            Vertex::new(dest.right(), dest.y, 0., uv.right(), uv.y, color),
+            Vertex::new(
+                dest.right(),
+                dest.bottom(),
+                0.,
+                uv.right(),
+                uv.bottom(),
+                color,
+            ),
+            Vertex::new(dest.x, dest.bottom(), 0., uv.x, uv.bottom(), color),
+        ];
+        self.submit(
+            &DrawState {
+                texture: Some(texture.clone()),
+                ..Default::default()
+            },
+            &vertices,
+            &[0, 1, 2, 0, 2, 3],
+        );
+    }
+
+    /// Draws the `source` part of `texture` (in texels, `None` for all of it) tinted by `tint`,
+    /// with its top-left corner at the origin of `tr` and one unit per texel.
+    pub fn draw_image(
+        &mut self,
+        texture: &Texture2D,
+        tr: &Transform,
+        source: Option<Rect>,
+        tint: Color,
+    ) {
+        let Some((width, height)) = self.backend.texture_size(texture) else {
+            warn!("Drawing a texture unknown to the backend");
+            return;
+        };
+        let source = source.unwrap_or(Rect::new(0., 0., width as f32, height as f32));
+        self.draw_image_grid(
+            texture,
+            tr,
+            (width as f32, height as f32),
+            [&[0., source.w], &[0., source.h]],
+            [&[source.x, source.right()], &[source.y, source.bottom()]],
+            tint,
+        );
+    }
+
+    /// Draws an image stretched to `dest` (in the coordinate system of `tr`) without
+    /// stretching its borders, as described by `slice`.
+    ///
+    /// Borders larger than `dest` shrink to fit.
+    pub fn draw_nine_slice(
+        &mut self,
+        texture: &Texture2D,
+        tr: &Transform,
+        dest: Rect,
+        slice: &NineSlice,
+        tint: Color,
+    ) {
+        let Some((width, height)) = self.backend.texture_size(texture) else {
+            warn!("Drawing a texture unknown to the backend");
+            return;
+        };
+        let source = slice
+            .source
+            .unwrap_or(Rect::new(0., 0., width as f32, height as f32));
+        let fit = |start: f32, end: f32, size: f32| {
+            let (start, end) = (start * slice.scale, end * slice.scale);
+            if start + end > size && start + end > 0. {
+                let shrink = size / (start + end);
+                (start * shrink, end * shrink)
+            } else {
+                (start, end)
+            }
+        };
+        let (left, right) = fit(slice.left, slice.right, dest.w);
+        let (top, bottom) = fit(slice.top, slice.bottom, dest.h);
+        self.draw_image_grid(
+            texture,
+            tr,
+            (width as f32, height as f32),
+            [
+                &[dest.x, dest.x + left, dest.right() - right, dest.right()],
+                &[dest.y, dest.y + top, dest.bottom() - bottom, dest.bottom()],
+            ],
+            [
+                &[
+                    source.x,
+                    source.x + slice.left,
+                    source.right() - slice.right,
+                    source.right(),
+                ],
+                &[
+                    source.y,
+                    source.y + slice.top,
+                    source.bottom() - slice.bottom,
+                    source.bottom(),
+                ],
+            ],
+            tint,
+        );
     }
 
+    /// Draws a grid of textured quads, `lines` being the x and y coordinates of the grid
+    /// lines in the coordinate system of `tr` and `texels` the matching ones in the texture.
+    fn draw_image_grid(
+        &mut self,
+        texture: &Texture2D,
+        tr: &Transform,
+        texture_size: (f32, f32),
+        [xs, ys]: [&[f32]; 2],
+        [us, vs]: [&[f32]; 2],
+        tint: Color,
+    ) {
+        let mut vertices = Vec::with_capacity(xs.len() * ys.len());
+        for (&y, &v) in ys.iter().zip(vs) {
+            for (&x, &u) in xs.iter().zip(us) {
+                let p = tr.transform_point(&Point::new(x, y));
+                vertices.push(Vertex::new(
+                    p.x,
+                    p.y,
+                    0.,
+                    u / texture_size.0,
+                    v / texture_size.1,
+                    tint,
+                ));
+            }
+        }
+        let columns = xs.len() as u16;
+        let mut indices = Vec::new();
+        for row in 0..ys.len() as u16 - 1 {
+            for column in 0..columns - 1 {
+                let a = row * columns + column;
+                let (b, c, d) = (a + 1, a + columns + 1, a + columns);
+                indices.extend_from_slice(&[a, b, c, a, c, d]);
+            }
+        }
+        self.submit(
+            &DrawState {
+                texture: Some(texture.clone()),
+                ..Default::default()
+            },
+            &vertices,
+            &indices,
+        );
+    }
+
+    /// Fills `path`, which is already in the final coordinate system.
+    ///
+    /// Paths with a transform should go through [`Self::fill_local_path`] instead,
+    /// which tessellates them just finely enough and reuses the result.
     pub fn fill_path(
         &mut self,
         path: impl IntoIterator<Item = PathEvent>,
         shading: impl Shading,
         alpha: f32,
     ) {
+        if self.anti_alias {
+            let mut builder = Path::builder();
+            for event in path {
+                builder.path_event(event);
+            }
+            let mut options = self.fill_options;
+            options.tolerance = self.tolerance_for(&Transform::identity());
+            let path = builder.build();
+            if let Some(geometry) = self.timed(|this| this.tessellate_fill(&path, &options)) {
+                let fringe = 1.0 / self.metrics.dpi_scale;
+                push_geometry(
+                    &mut self.vertex_buffers,
+                    &geometry,
+                    &Transform::identity(),
+                    &shading,
+                    alpha,
+                    fringe,
+                );
+                self.emit_lyon(&DrawState::of(&shading));
+            }
+            return;
+        }
         self.draw_lyon(shading, alpha, |this, shading| {
             this.fill_tessellator
                 .tessellate(
@@ -178,21 +1104,423 @@ impl Window {
         });
     }
 
-    pub fn stroke_path(&mut self, path: &Path, shading: impl Shading, alpha: f32, thickness: f32) {
+    /// Strokes `path`, which is already in the final coordinate system.
+    ///
+    /// See [`Self::stroke_local_path`] for paths with a transform.
+    pub fn stroke_path(
+        &mut self,
+        path: &Path,
+        shading: impl Shading,
+        alpha: f32,
+        style: impl Into<StrokeStyle>,
+    ) {
+        let style = style.into();
+        let tolerance = self.tolerance_for(&Transform::identity());
+        let dashed = style.is_dashed().then(|| style.dash(path, tolerance));
+        let options = style.options(tolerance);
         self.draw_lyon(shading, alpha, |this, shaded| {
-            this.stroke_options.line_width = thickness;
             this.stroke_tessellator
                 .tessellate_path(
-                    path,
-                    &this.stroke_options,
+                    dashed.as_ref().unwrap_or(path),
+                    &options,
                     &mut BuffersBuilder::new(&mut this.vertex_buffers, shaded),
                 )
                 .unwrap();
         });
     }
-    pub fn update(&mut self) {
-        self.pixel_width = screen_width() as u32;
-        self.pixel_height = screen_height() as u32;
-        self.logical_ppi = screen_dpi_scale();
+    /// Fills `path`, which is in the coordinate system of `tr`.
+    ///
+    /// The path is tessellated in its own coordinate system, as finely as the scale of `tr`
+    /// requires, and cached: drawing it again only costs transforming its vertices,
+    /// even if `tr` changed.
+    pub fn fill_local_path(
+        &mut self,
+        path: &Path,
+        tr: &Transform,
+        shading: impl Shading,
+        alpha: f32,
+    ) {
+        let mut options = self.fill_options;
+        options.tolerance = self.tolerance_for(tr);
+        let key = fill_key(path, &options, self.anti_alias);
+        self.draw_cached(key, tr, shading, alpha, |this| {
+            this.tessellate_fill(path, &options)
+        });
+    }
+
+    /// Fill triangles of `path`, with a fringe if anti-aliasing is on.
+    fn tessellate_fill(&mut self, path: &Path, options: &FillOptions) -> Option<Geometry> {
+        let mut buffers: VertexBuffers<_, u16> = VertexBuffers::new();
+        self.fill_tessellator
+            .tessellate_path(
+                path,
+                options,
+                &mut BuffersBuilder::new(&mut buffers, |v: FillVertex| v.position()),
+            )
+            .inspect_err(|e| warn!("Failed to tessellate path: {:?}", e))
+            .ok()?;
+        let mut geometry = Geometry {
+            vertices: buffers.vertices,
+            indices: buffers.indices,
+            ..Default::default()
+        };
+        if self.anti_alias {
+            add_fringe(&mut self.stroke_tessellator, path, options, &mut geometry);
+        }
+        Some(geometry)
+    }
+
+    /// Strokes `path`, which is in the coordinate system of `tr`, like [`Self::fill_local_path`].
+    ///
+    /// The width and dashes of `style` are in the coordinate system of `tr` as well.
+    pub fn stroke_local_path(
+        &mut self,
+        path: &Path,
+        tr: &Transform,
+        shading: impl Shading,
+        alpha: f32,
+        style: impl Into<StrokeStyle>,
+    ) {
+        let style = style.into();
+        let tolerance = self.tolerance_for(tr);
+        let options = style.options(tolerance);
+        let key = stroke_key(path, &options, &style);
+        self.draw_cached(key, tr, shading, alpha, |this| {
+            let dashed = style.is_dashed().then(|| style.dash(path, tolerance));
+            let mut buffers: VertexBuffers<_, u16> = VertexBuffers::new();
+            this.stroke_tessellator
+                .tessellate_path(
+                    dashed.as_ref().unwrap_or(path),
+                    &options,
+                    &mut BuffersBuilder::new(&mut buffers, |v: StrokeVertex| v.position()),
+                )
+                .inspect_err(|e| warn!("Failed to tessellate path: {:?}", e))
+                .ok()?;
+            Some(Geometry {
+                vertices: buffers.vertices,
+                indices: buffers.indices,
+                ..Default::default()
+            })
+        });
+    }
+
+    /// Fills `rect`, which is in the coordinate system of `tr`.
+    pub fn fill_rect(&mut self, rect: &Box2D, tr: &Transform, shading: impl Shading, alpha: f32) {
+        let mut builder = Path::builder();
+        builder.add_rectangle(rect, Winding::Positive);
+        self.fill_local_path(&builder.build(), tr, shading, alpha);
+    }
+
+    /// Strokes `rect`, which is in the coordinate system of `tr` like `style`.
+    pub fn stroke_rect(
+        &mut self,
+        rect: &Box2D,
+        tr: &Transform,
+        shading: impl Shading,
+        alpha: f32,
+        style: impl Into<StrokeStyle>,
+    ) {
+        let mut builder = Path::builder();
+        builder.add_rectangle(rect, Winding::Positive);
+        self.stroke_local_path(&builder.build(), tr, shading, alpha, style);
+    }
+
+    /// Fills `rect` with rounded corners, all in the coordinate system of `tr`.
+    pub fn fill_rounded_rect(
+        &mut self,
+        rect: &Box2D,
+        radii: &BorderRadii,
+        tr: &Transform,
+        shading: impl Shading,
+        alpha: f32,
+    ) {
+        let mut builder = Path::builder();
+        builder.add_rounded_rectangle(rect, radii, Winding::Positive);
+        self.fill_local_path(&builder.build(), tr, shading, alpha);
+    }
+
+    /// Strokes `rect` with rounded corners, all in the coordinate system of `tr`.
+    pub fn stroke_rounded_rect(
+        &mut self,
+        rect: &Box2D,
+        radii: &BorderRadii,
+        tr: &Transform,
+        shading: impl Shading,
+        alpha: f32,
+        style: impl Into<StrokeStyle>,
+    ) {
+        let mut builder = Path::builder();
+        builder.add_rounded_rectangle(rect, radii, Winding::Positive);
+        self.stroke_local_path(&builder.build(), tr, shading, alpha, style);
+    }
+
+    /// Fills a circle, in the coordinate system of `tr`.
+    pub fn fill_circle(
+        &mut self,
+        center: lyon::math::Point,
+        radius: f32,
+        tr: &Transform,
+        shading: impl Shading,
+        alpha: f32,
+    ) {
+        let mut builder = Path::builder();
+        builder.add_circle(center, radius, Winding::Positive);
+        self.fill_local_path(&builder.build(), tr, shading, alpha);
+    }
+
+    /// Fills an axis-aligned ellipse, in the coordinate system of `tr`.
+    pub fn fill_ellipse(
+        &mut self,
+        center: lyon::math::Point,
+        radii: Vector,
+        tr: &Transform,
+        shading: impl Shading,
+        alpha: f32,
+    ) {
+        let mut builder = Path::builder();
+        builder.add_ellipse(center, radii, Angle::zero(), Winding::Positive);
+        self.fill_local_path(&builder.build(), tr, shading, alpha);
+    }
+
+    /// Strokes `arc`, which is in the coordinate system of `tr` like `style`.
+    pub fn stroke_arc(
+        &mut self,
+        arc: &Arc<f32>,
+        tr: &Transform,
+        shading: impl Shading,
+        alpha: f32,
+        style: impl Into<StrokeStyle>,
+    ) {
+        let mut builder = Path::builder();
+        builder.begin(arc.from());
+        arc.for_each_quadratic_bezier(&mut |curve| {
+            builder.quadratic_bezier_to(curve.ctrl, curve.to);
+        });
+        builder.end(false);
+        self.stroke_local_path(&builder.build(), tr, shading, alpha, style);
+    }
+
+    /// Strokes a line segment, in the coordinate system of `tr` like `style`.
+    pub fn stroke_line(
+        &mut self,
+        from: lyon::math::Point,
+        to: lyon::math::Point,
+        tr: &Transform,
+        shading: impl Shading,
+        alpha: f32,
+        style: impl Into<StrokeStyle>,
+    ) {
+        let mut builder = Path::builder();
+        builder.begin(from);
+        builder.line_to(to);
+        builder.end(false);
+        self.stroke_local_path(&builder.build(), tr, shading, alpha, style);
+    }
+
+    /// Fills the polygon through `points`, which are in the coordinate system of `tr`.
+    pub fn fill_polygon(
+        &mut self,
+        points: &[lyon::math::Point],
+        tr: &Transform,
+        shading: impl Shading,
+        alpha: f32,
+    ) {
+        if points.is_empty() {
+            return;
+        }
+        let mut builder = Path::builder();
+        builder.add_polygon(Polygon {
+            points,
+            closed: true,
+        });
+        self.fill_local_path(&builder.build(), tr, shading, alpha);
+    }
+
+    /// Strokes the outline of the polygon through `points`, like [`Self::fill_polygon`].
+    pub fn stroke_polygon(
+        &mut self,
+        points: &[lyon::math::Point],
+        tr: &Transform,
+        shading: impl Shading,
+        alpha: f32,
+        style: impl Into<StrokeStyle>,
+    ) {
+        if points.is_empty() {
+            return;
+        }
+        let mut builder = Path::builder();
+        builder.add_polygon(Polygon {
+            points,
+            closed: true,
+        });
+        self.stroke_local_path(&builder.build(), tr, shading, alpha, style);
+    }
+
+    /// Draws the cached geometry for `key`, calling `tessellate` to create it if needed.
+    fn draw_cached<S: Shading>(
+        &mut self,
+        key: u64,
+        tr: &Transform,
+        shading: S,
+        alpha: f32,
+        tessellate: impl FnOnce(&mut Self) -> Option<Geometry>,
+    ) {
+        if self.tessellation_cache.get(key).is_none() {
+            let Some(geometry) = self.timed(tessellate) else {
+                return;
+            };
+            self.tessellation_cache.insert(key, geometry);
+        }
+        let Some(geometry) = self.tessellation_cache.peek(key) else {
+            return;
+        };
+        // One physical pixel, in the coordinate system of the path
+        let fringe = 1.0 / (self.metrics.dpi_scale * transform_scale(tr));
+        push_geometry(
+            &mut self.vertex_buffers,
+            geometry,
+            tr,
+            &shading,
+            alpha,
+            fringe,
+        );
+        self.emit_lyon(&DrawState::of(&shading));
+    }
+
+    /// Starts recording everything drawn into a [`DisplayList`], until the matching
+    /// [`Self::end_recording`]. Recordings nest, see [`Retained`](crate::retained::Retained).
+    pub fn begin_recording(&mut self) {
+        let evictions = self.font_atlas.evictions();
+        self.recordings.push((
+            DisplayList {
+                replayable: true,
+                ..Default::default()
+            },
+            evictions,
+        ));
+    }
+
+    /// Ends the last [`Self::begin_recording`], returning what was drawn in between
+    /// unless it can't be replayed.
+    pub fn end_recording(&mut self) -> Option<DisplayList> {
+        let (mut list, evictions) = self.recordings.pop()?;
+        // Glyphs drawn early in the recording may have been evicted by later ones
+        list.atlas_evictions = self.font_atlas.evictions();
+        (list.replayable && list.atlas_evictions == evictions).then_some(list)
+    }
+
+    /// Draws `list` again, returning false without drawing anything if it is out of date.
+    pub fn replay(&mut self, list: &DisplayList) -> bool {
+        if list.atlas_evictions != self.font_atlas.evictions() {
+            return false;
+        }
+        for command in &list.commands {
+            match command {
+                DisplayCommand::Draw {
+                    state,
+                    vertices,
+                    indices,
+                } => self.submit(state, vertices, indices),
+                DisplayCommand::PushClip(clip, tr) => self.push_clip(clip, tr),
+                DisplayCommand::PopClip => self.pop_clip(),
+                DisplayCommand::PushLayer => self.push_layer(),
+                DisplayCommand::PopLayer(options) => self.pop_layer(options),
+            }
+        }
+        true
+    }
+
+    fn record(&mut self, command: impl FnOnce() -> DisplayCommand) {
+        let Some(((innermost, _), outer)) = self.recordings.split_last_mut() else {
+            return;
+        };
+        let command = command();
+        for (list, _) in outer {
+            list.commands.push(command.clone());
+        }
+        innermost.commands.push(command);
+    }
+
+    fn invalidate_recordings(&mut self) {
+        for (list, _) in &mut self.recordings {
+            list.replayable = false;
+        }
+    }
+
+    /// Counters of the last frame ended by [`Self::update`].
+    pub fn frame_stats(&self) -> &FrameStats {
+        &self.last_stats
+    }
+
+    /// Counts a label laid out again in [`FrameStats::labels_laid_out`].
+    pub(crate) fn count_label_layout(&mut self) {
+        self.stats.labels_laid_out += 1;
+    }
+
+    /// Flushes the current frame, ending its [`FrameStats`], and picks up the new screen
+    /// size and DPI scale.
+    ///
+    /// Returns the new metrics if they changed, to pass on to [`Component::resize`].
+    pub fn update(&mut self) -> Option<ScreenMetrics> {
+        self.flush();
+        let now = Instant::now();
+        let frame = self.last_stats.frame + 1;
+        self.last_stats = FrameStats {
+            frame,
+            frame_time: now - self.frame_start,
+            atlas_occupancy: self.font_atlas.occupancy(),
+            ..std::mem::take(&mut self.stats)
+        };
+        self.frame_start = now;
+
+        let metrics = ScreenMetrics {
+            size: self.backend.screen_size(),
+            dpi_scale: self.backend.dpi_scale(),
+        };
+        if metrics == self.metrics {
+            return None;
+        }
+        self.metrics = metrics;
+        Some(metrics)
+    }
+}
+
+/// Adds `geometry`, transformed by `tr`, to `buffers`.
+///
+/// `fringe` is the width of the anti-aliasing fringe in the coordinate system of `geometry`.
+fn push_geometry(
+    buffers: &mut VertexBuffers<Vertex, u16>,
+    geometry: &Geometry,
+    tr: &Transform,
+    shading: &impl Shading,
+    alpha: f32,
+    fringe: f32,
+) {
+    let offset = buffers.vertices.len() as u16;
+    buffers
+        .vertices
+        .extend(geometry.vertices.iter().enumerate().map(|(i, p)| {
+            let (p, alpha) = match geometry.outsets.get(i) {
+                Some(outset) if *outset != Vector::zero() => (*p + *outset * fringe, 0.0),
+                _ => (*p, alpha),
+            };
+            shading.new_vertex(&tr.transform_point(&Point::new(p.x, p.y)), alpha)
+        }));
+    buffers
+        .indices
+        .extend(geometry.indices.iter().map(|&i| i + offset));
+}
+
+/// How much `tr` scales lengths, at most.
+fn transform_scale(tr: &Transform) -> f32 {
+    let x = (tr[(0, 0)] * tr[(0, 0)] + tr[(1, 0)] * tr[(1, 0)]).sqrt();
+    let y = (tr[(0, 1)] * tr[(0, 1)] + tr[(1, 1)] * tr[(1, 1)]).sqrt();
+    let scale = x.max(y);
+    if scale.is_normal() { scale } else { 1.0 }
+}
+
+impl Drop for Window {
+    fn drop(&mut self) {
+        self.flush();
     }
 }
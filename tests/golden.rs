@@ -0,0 +1,416 @@
+//! Renders components with [`SoftwareBackend`] and compares them with the images in
+//! `tests/golden`. Run with `COMUI_UPDATE_GOLDEN=1` to rewrite them.
+
+use std::sync::Arc;
+
+use comui::{
+    backend::SoftwareBackend,
+    component::Component,
+    components::{button::QuadButton, icon::Icon, label::Label},
+    image::NineSlice,
+    layout::{Layout, LayoutBuilder},
+    scene::{NextScene, Scene, SceneManager},
+    shading::{GradientShading, IntoShading},
+    shadow::Shadow,
+    svg::Svg,
+    utils::Transform,
+    window::Window,
+};
+use lyon::{
+    math::{Box2D, point},
+    path::builder::BorderRadii,
+};
+use macroquad::{
+    color::Color,
+    input::{Touch, TouchPhase},
+    math::{Rect, vec2},
+    texture::Image,
+};
+use nalgebra::Vector2;
+
+const TOLERANCE: u8 = 2;
+
+fn window(width: f32, height: f32) -> Window {
+    let mut window = Window::new(SoftwareBackend::new(width, height));
+    // System fonts differ between machines
+    let db = window.font_system_mut().db_mut();
+    db.load_font_data(include_bytes!("fonts/FiraMono-Medium.ttf").to_vec());
+    db.set_sans_serif_family("Fira Mono");
+    window
+}
+
+fn check(window: &mut Window, name: &str) {
+    window.flush();
+    let path = format!("{}/tests/golden/{name}.png", env!("CARGO_MANIFEST_DIR"));
+    let backend = window.backend::<SoftwareBackend>().unwrap();
+    if let Err(e) = backend.check_golden(&path, TOLERANCE) {
+        panic!("{e:#}");
+    }
+}
+
+fn clear(window: &mut Window) {
+    window
+        .backend_mut::<SoftwareBackend>()
+        .unwrap()
+        .clear(Color::new(0., 0., 0., 1.));
+}
+
+#[test]
+fn label() {
+    let mut window = window(160., 60.);
+    clear(&mut window);
+    let mut label = Label::new("comui\nlabel")
+        .with_font_size(20.)
+        .with_line_height(24.)
+        .with_color(Color::new(1., 0.9, 0.3, 1.));
+    label.render(
+        &Transform::new_translation(&Vector2::new(80., 30.)),
+        &mut window,
+    );
+    check(&mut window, "label");
+}
+
+/// A rounded rectangle that darkens while pressed.
+#[derive(Default)]
+struct Button {
+    inner: QuadButton,
+}
+
+impl Layout for Button {
+    fn components(&mut self) -> Vec<(Transform, &mut dyn Component)> {
+        LayoutBuilder::new()
+            .at_rect((0., 0., 1., 1.), &mut self.inner)
+            .build()
+    }
+
+    fn before_render(&mut self, tr: &Transform, target: &mut Window) {
+        let color = if self.inner.touch_id.is_some() {
+            Color::new(0.1, 0.3, 0.6, 1.)
+        } else {
+            Color::new(0.2, 0.5, 0.9, 1.)
+        };
+        target.fill_rounded_rect(
+            &Box2D::new(point(-0.5, -0.5), point(0.5, 0.5)),
+            &BorderRadii::new(0.2),
+            tr,
+            color.into_shading(),
+            1.,
+        );
+    }
+}
+
+#[test]
+fn button() {
+    let mut window = window(100., 60.);
+    let tr = Transform::new_translation(&Vector2::new(50., 30.))
+        * Transform::new_nonuniform_scaling(&Vector2::new(80., 40.));
+    let mut button = Button::default();
+
+    clear(&mut window);
+    button.render(&tr, &mut window);
+    check(&mut window, "button");
+
+    // Touches are in the coordinate system of the button
+    let touch = |phase| Touch {
+        id: 1,
+        phase,
+        position: vec2(0., 0.),
+    };
+    button.touch(&touch(TouchPhase::Started)).unwrap();
+    clear(&mut window);
+    button.render(&tr, &mut window);
+    check(&mut window, "button_pressed");
+
+    assert!(button.touch(&touch(TouchPhase::Ended)).unwrap());
+    assert!(button.inner.triggered);
+    clear(&mut window);
+    button.render(&tr, &mut window);
+    check(&mut window, "button");
+}
+
+/// Fills the screen and draws a square, then changes to `next` once.
+struct ColorScene {
+    background: Color,
+    square: Color,
+    next: Option<NextScene>,
+}
+
+impl ColorScene {
+    fn new(background: Color, square: Color) -> Self {
+        Self {
+            background,
+            square,
+            next: None,
+        }
+    }
+}
+
+impl Layout for ColorScene {
+    fn components(&mut self) -> Vec<(Transform, &mut dyn Component)> {
+        Vec::new()
+    }
+
+    fn before_render(&mut self, tr: &Transform, target: &mut Window) {
+        let (width, height) = target.logical_size();
+        target.fill_rect(
+            &Box2D::new(point(0., 0.), point(width, height)),
+            tr,
+            self.background.into_shading(),
+            1.,
+        );
+        target.fill_rect(
+            &Box2D::new(point(20., 20.), point(60., 60.)),
+            tr,
+            self.square.into_shading(),
+            1.,
+        );
+    }
+}
+
+impl Scene for ColorScene {
+    fn next_scene(&mut self) -> Option<NextScene> {
+        self.next.take()
+    }
+}
+
+#[test]
+fn scene_stack() {
+    let mut window = window(80., 80.);
+    let mut pushed = ColorScene::new(Color::new(0.1, 0.1, 0.2, 1.), Color::new(0.2, 0.8, 0.3, 1.));
+    pushed.next = Some(NextScene::Pop);
+    let mut base = ColorScene::new(Color::new(0.9, 0.9, 0.9, 1.), Color::new(0.8, 0.1, 0.1, 1.));
+    base.next = Some(NextScene::Push(Box::new(pushed)));
+    let mut manager = SceneManager::new(base);
+
+    // Scenes change after rendering
+    manager.render(&Transform::identity(), &mut window);
+    check(&mut window, "scene_base");
+    assert_eq!(manager.scene_stack.len(), 2);
+
+    manager.render(&Transform::identity(), &mut window);
+    check(&mut window, "scene_pushed");
+    assert_eq!(manager.scene_stack.len(), 1);
+
+    manager.render(&Transform::identity(), &mut window);
+    check(&mut window, "scene_base");
+}
+
+/// A rectangle in the coordinate system of its parent.
+struct Patch(Box2D, Color);
+
+impl Component for Patch {
+    fn touch(&mut self, _: &Touch) -> anyhow::Result<bool> {
+        Ok(false)
+    }
+
+    fn render(&mut self, tr: &Transform, target: &mut Window) {
+        target.fill_rect(&self.0, tr, self.1.into_shading(), 1.);
+    }
+}
+
+#[test]
+fn capture_component() {
+    let mut window = Window::new(SoftwareBackend::new(60., 40.).with_dpi_scale(2.));
+    clear(&mut window);
+    window.fill_rect(
+        &Box2D::new(point(0., 0.), point(30., 40.)),
+        &Transform::identity(),
+        Color::new(0., 0., 1., 1.).into_shading(),
+        1.,
+    );
+    window.flush();
+    let screen = window.backend::<SoftwareBackend>().unwrap().image();
+
+    let mut patch = Patch(
+        Box2D::new(point(0., 0.), point(20., 20.)),
+        Color::new(1., 0.5, 0., 0.5),
+    );
+    let image = window.capture_component(
+        &mut patch,
+        &Transform::identity(),
+        Some(macroquad::math::Rect::new(10., 10., 20., 10.)),
+    );
+    // In physical pixels
+    assert_eq!((image.width, image.height), (40, 20));
+    let pixel = |x: usize, y: usize| {
+        let i = (y * image.width as usize + x) * 4;
+        <[u8; 4]>::try_from(&image.bytes[i..i + 4]).unwrap()
+    };
+    // Left half covered by the patch, not premultiplied, the rest transparent
+    for y in [0, 10, 19] {
+        for x in [0, 10, 17] {
+            let close = pixel(x, y)
+                .iter()
+                .zip([255, 128, 0, 128])
+                .all(|(a, b)| a.abs_diff(b) <= TOLERANCE);
+            assert!(close, "{:?} at ({x}, {y})", pixel(x, y));
+        }
+        for x in [22, 30, 39] {
+            assert_eq!(pixel(x, y), [0; 4]);
+        }
+    }
+
+    window.flush();
+    let after = window.backend::<SoftwareBackend>().unwrap().image();
+    assert_eq!(after.bytes, screen.bytes, "the screen changed");
+}
+
+fn light(window: &mut Window) {
+    window
+        .backend_mut::<SoftwareBackend>()
+        .unwrap()
+        .clear(Color::new(0.9, 0.9, 0.9, 1.));
+}
+
+#[test]
+fn shadow() {
+    let mut window = window(120., 80.);
+    light(&mut window);
+    let mut builder = lyon::path::Path::builder();
+    builder.add_circle(point(0., 0.), 1., lyon::path::Winding::Positive);
+    let circle = builder.build();
+    let tr = Transform::new_translation(&Vector2::new(40., 40.)) * Transform::new_scaling(20.);
+    let shadow = Shadow::new(vec2(4., 6.), 8.);
+    window.draw_shadow(&circle, &tr, &shadow, Color::new(0., 0., 0.4, 0.6));
+    window.fill_local_path(&circle, &tr, Color::new(1., 1., 1., 1.).into_shading(), 1.);
+
+    // Spread grows the shape before blurring
+    let tr = Transform::new_translation(&Vector2::new(90., 40.)) * Transform::new_scaling(12.);
+    let shadow = Shadow::new(vec2(0., 0.), 4.).with_spread(6.);
+    window.draw_shadow(&circle, &tr, &shadow, Color::new(0.8, 0.1, 0.1, 1.));
+    window.fill_local_path(&circle, &tr, Color::new(1., 1., 1., 1.).into_shading(), 1.);
+    check(&mut window, "shadow");
+}
+
+#[test]
+fn box_shadow() {
+    let mut window = window(120., 80.);
+    light(&mut window);
+    let rect = Box2D::new(point(-20., -15.), point(20., 15.));
+    let radii = BorderRadii::new(6.);
+    let tr = Transform::new_translation(&Vector2::new(35., 40.));
+    window.draw_box_shadow(
+        &rect,
+        &radii,
+        &tr,
+        &Shadow::new(vec2(3., 5.), 10.),
+        Color::new(0., 0., 0., 0.5).into_shading(),
+    );
+    window.fill_rounded_rect(
+        &rect,
+        &radii,
+        &tr,
+        Color::new(1., 1., 1., 1.).into_shading(),
+        1.,
+    );
+
+    // Any shading colors the shadow, here a gradient across it
+    let tr = Transform::new_translation(&Vector2::new(88., 40.)) * Transform::new_scaling(0.6);
+    let gradient = GradientShading::linear(
+        nalgebra::Point2::new(60., 0.),
+        nalgebra::Point2::new(116., 0.),
+        [
+            (0., Color::new(1., 0., 0., 1.)),
+            (1., Color::new(0., 0., 1., 1.)),
+        ],
+    );
+    window.draw_box_shadow(
+        &rect,
+        &radii,
+        &tr,
+        &Shadow::new(vec2(0., 0.), 12.).with_spread(2.),
+        gradient,
+    );
+    window.fill_rounded_rect(
+        &rect,
+        &radii,
+        &tr,
+        Color::new(1., 1., 1., 1.).into_shading(),
+        1.,
+    );
+    check(&mut window, "box_shadow");
+}
+
+const ICON: &str = r##"<svg xmlns="http://www.w3.org/2000/svg" width="40" height="40">
+  <defs>
+    <linearGradient id="sky" x1="0" y1="0" x2="0" y2="1">
+      <stop offset="0" stop-color="#2060ff"/>
+      <stop offset="1" stop-color="#20e0a0" stop-opacity="0.5"/>
+    </linearGradient>
+  </defs>
+  <rect x="2" y="2" width="36" height="36" rx="6" fill="url(#sky)"/>
+  <g transform="translate(20 20) scale(1.5)">
+    <path fill="#ffd040" fill-rule="evenodd" d="M-8 0a8 8 0 1 0 16 0a8 8 0 1 0-16 0zM-4 0a4 4 0 1 0 8 0a4 4 0 1 0-8 0z"/>
+  </g>
+  <path d="M6 34L16 24L24 30L34 8" fill="none" stroke="#ff3050" stroke-width="3"
+    stroke-linecap="round" stroke-linejoin="round"/>
+</svg>"##;
+
+#[test]
+fn icon() {
+    let mut window = window(160., 80.);
+    clear(&mut window);
+    let svg = Arc::new(Svg::from_data(ICON.as_bytes()).unwrap());
+    let tr =
+        |x: f32| Transform::new_translation(&Vector2::new(x, 40.)) * Transform::new_scaling(70.);
+    Icon::new(svg.clone()).render(&tr(40.), &mut window);
+    // Translucent, so that the shapes show where they overlap
+    Icon::new(svg)
+        .with_color(Color::new(1., 1., 1., 0.5))
+        .render(&tr(120.), &mut window);
+    check(&mut window, "icon");
+}
+
+/// 12x12 texels cut in 4x4 cells: checkered corners, green edges and a blue center.
+fn nine_slice_image() -> Image {
+    let mut bytes = Vec::new();
+    for y in 0..12 {
+        for x in 0..12 {
+            let cell = |i: usize| i / 4 == 1;
+            bytes.extend_from_slice(&match (cell(x), cell(y)) {
+                (true, true) => [40, 80, 220, 255],
+                (false, false) if (x + y) % 2 == 0 => [255, 255, 255, 255],
+                (false, false) => [220, 40, 40, 255],
+                _ => [40, 180, 60, 255],
+            });
+        }
+    }
+    Image {
+        bytes,
+        width: 12,
+        height: 12,
+    }
+}
+
+#[test]
+fn nine_slice() {
+    let mut window = window(160., 80.);
+    clear(&mut window);
+    let texture = window
+        .backend_mut::<SoftwareBackend>()
+        .unwrap()
+        .add_texture(nine_slice_image());
+    let white = Color::new(1., 1., 1., 1.);
+    // Corners stay 8x8 while the edges and the center stretch
+    let slice = NineSlice::uniform(4.).with_scale(2.);
+    window.draw_nine_slice(
+        &texture,
+        &Transform::identity(),
+        Rect::new(8., 8., 80., 50.),
+        &slice,
+        white,
+    );
+    // Insets of 24 don't fit in 40x30, they shrink to 20 and 15
+    let slice = NineSlice::uniform(4.).with_scale(6.);
+    window.draw_nine_slice(
+        &texture,
+        &Transform::identity(),
+        Rect::new(110., 8., 40., 30.),
+        &slice,
+        white,
+    );
+    // A corner alone, scaled up
+    let tr = Transform::new_translation(&Vector2::new(110., 48.)) * Transform::new_scaling(6.);
+    window.draw_image(&texture, &tr, Some(Rect::new(0., 0., 4., 4.)), white);
+    check(&mut window, "nine_slice");
+}
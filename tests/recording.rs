@@ -0,0 +1,505 @@
+use comui::{
+    backend::{Command, RecordingBackend},
+    clip::Clip,
+    component::Component,
+    components::label::Label,
+    layout::{Layout, LayoutBuilder},
+    retained::Retained,
+    scene::{NextScene, Scene, SceneManager},
+    shading::{GradientShading, IntoShading},
+    utils::{Point, Transform},
+    window::{VertexBuilder, Window},
+};
+use lyon::{
+    math::{Box2D, point},
+    path::builder::BorderRadii,
+};
+use macroquad::{color::Color, input::TouchPhase, math::vec2, prelude::Touch};
+use std::{cell::Cell, rc::Rc, time::Duration};
+
+/// A unit square around the origin of its coordinate system.
+struct Square(Color);
+
+impl Component for Square {
+    fn touch(&mut self, _touch: &Touch) -> anyhow::Result<bool> {
+        Ok(false)
+    }
+
+    fn render(&mut self, tr: &Transform, target: &mut Window) {
+        let corner = |x, y| tr.transform_point(&Point::new(x, y));
+        target.fill_quad(
+            [
+                corner(-0.5, -0.5),
+                corner(0.5, -0.5),
+                corner(0.5, 0.5),
+                corner(-0.5, 0.5),
+            ],
+            self.0.into_shading(),
+            1.,
+        );
+    }
+}
+
+struct Pair(Square, Square);
+
+impl Layout for Pair {
+    fn components(&mut self) -> Vec<(Transform, &mut dyn Component)> {
+        LayoutBuilder::new()
+            .at_rect((25., 50., 50., 100.), &mut self.0)
+            .at_rect((75., 50., 50., 100.), &mut self.1)
+            .build()
+    }
+}
+
+fn recorder(window: &Window) -> &RecordingBackend {
+    window.backend::<RecordingBackend>().unwrap()
+}
+
+#[test]
+fn records_the_geometry_of_a_component() {
+    let mut window = Window::new(RecordingBackend::new(100., 100.));
+    let tr =
+        Transform::new_translation(&nalgebra::Vector2::new(50., 50.)) * Transform::new_scaling(20.);
+    Square(Color::new(1., 0., 0., 1.)).render(&tr, &mut window);
+    window.flush();
+
+    let calls = recorder(&window).draw_calls().collect::<Vec<_>>();
+    assert_eq!(calls.len(), 1);
+    let (vertices, indices, state) = calls[0];
+    assert_eq!(vertices.len(), 4);
+    assert_eq!(indices.len(), 6);
+    assert!(state.texture.is_none());
+    let xs = vertices.iter().map(|v| v.position.x);
+    assert_eq!(xs.clone().fold(f32::MAX, f32::min), 40.);
+    assert_eq!(xs.fold(f32::MIN, f32::max), 60.);
+    assert!(vertices.iter().all(|v| v.color == [255, 0, 0, 255]));
+}
+
+#[test]
+fn batches_the_children_of_a_layout() {
+    let mut window = Window::new(RecordingBackend::new(100., 100.));
+    let mut pair = Pair(
+        Square(Color::new(1., 0., 0., 1.)),
+        Square(Color::new(0., 0., 1., 1.)),
+    );
+    pair.render(&Transform::identity(), &mut window);
+    assert_eq!(
+        recorder(&window).draw_calls().count(),
+        0,
+        "drawn before flushing"
+    );
+    window.flush();
+
+    let calls = recorder(&window).draw_calls().collect::<Vec<_>>();
+    assert_eq!(calls.len(), 1);
+    let (vertices, indices, _) = calls[0];
+    assert_eq!(vertices.len(), 8);
+    assert_eq!(indices.len(), 12);
+    assert!(
+        recorder(&window)
+            .commands
+            .iter()
+            .all(|command| !matches!(command, Command::PushStencil { .. }))
+    );
+}
+
+#[test]
+fn splits_paths_too_large_for_one_submission() {
+    let mut backend = RecordingBackend::new(400., 400.);
+    backend.max_submission_size = (100, 150);
+    let mut window = Window::new(backend);
+    let mut builder = lyon::path::Path::builder();
+    builder.add_circle(lyon::math::point(0., 0.), 1., lyon::path::Winding::Positive);
+    // Fine enough for hundreds of triangles
+    let tr = Transform::new_translation(&nalgebra::Vector2::new(200., 200.))
+        * Transform::new_scaling(190.);
+    window.fill_local_path(
+        &builder.build(),
+        &tr,
+        Color::new(1., 1., 1., 1.).into_shading(),
+        1.,
+    );
+    window.flush();
+
+    let calls = recorder(&window).draw_calls().collect::<Vec<_>>();
+    assert!(calls.len() > 1);
+    let mut triangles = 0;
+    for (vertices, indices, _) in calls {
+        assert!(vertices.len() <= 100 && indices.len() <= 150);
+        assert!(indices.iter().all(|&i| (i as usize) < vertices.len()));
+        triangles += indices.len() / 3;
+    }
+    assert!(triangles > 50);
+}
+
+#[test]
+fn lays_labels_out_again_after_a_dpi_change() {
+    let mut window = Window::new(RecordingBackend::new(100., 100.));
+    let mut label = Label::new("comui");
+    let frame = |label: &mut Label, window: &mut Window| {
+        label.render(&Transform::identity(), window);
+        let metrics = window.update();
+        (window.frame_stats().labels_laid_out, metrics)
+    };
+
+    assert_eq!(frame(&mut label, &mut window), (1, None));
+    assert_eq!(frame(&mut label, &mut window), (0, None));
+    assert!(!label.is_dirty());
+    let height = label.computed_height();
+
+    window.backend_mut::<RecordingBackend>().unwrap().dpi_scale = 2.;
+    let (_, metrics) = frame(&mut label, &mut window);
+    label.resize(&metrics.expect("the DPI scale changed"));
+    assert!(label.is_dirty());
+    assert_eq!(frame(&mut label, &mut window).0, 1);
+    assert!(label.computed_height() > height * 1.5);
+}
+
+/// Separate quads of 4 vertices each in a row, `y` from 0 to 1.
+fn quads(window: &Window, count: u32) -> VertexBuilder<GradientShading> {
+    let mut builder = window.vertex_builder(Color::new(1., 1., 1., 1.).into_shading());
+    for i in 0..count {
+        let (x, first) = (i as f32, builder.len());
+        builder = builder
+            .add(x, 0., 1.)
+            .add(x + 1., 0., 1.)
+            .add(x + 1., 1., 1.)
+            .add(x, 1., 1.)
+            .triangle(first, first + 1, first + 2)
+            .triangle(first, first + 2, first + 3);
+    }
+    builder
+}
+
+/// Every index of every draw call refers to a vertex of the call, returns the triangles.
+fn checked_triangles(window: &Window) -> usize {
+    let mut triangles = 0;
+    for (vertices, indices, _) in recorder(window).draw_calls() {
+        assert!(indices.iter().all(|&i| (i as usize) < vertices.len()));
+        triangles += indices.len() / 3;
+    }
+    triangles
+}
+
+#[test]
+fn splits_vertex_builders_beyond_u16_indices() {
+    let mut window = Window::new(RecordingBackend::new(100., 100.));
+    const QUADS: u32 = 20_000;
+    let mut builder = window.vertex_builder(Color::new(1., 1., 1., 1.).into_shading());
+    for i in 0..QUADS {
+        let (x, first) = (i as f32, builder.len());
+        let u = |x: f32| x / QUADS as f32;
+        builder = builder
+            .add_textured(x, 0., u(x), 0., 1.)
+            .add_textured(x + 1., 0., u(x + 1.), 0., 1.)
+            .add_textured(x + 1., 1., u(x + 1.), 1., 1.)
+            .add_textured(x, 1., u(x), 1., 1.)
+            .triangle(first, first + 1, first + 2)
+            .triangle(first, first + 2, first + 3);
+    }
+    assert!(builder.len() > u16::MAX as u32 + 1);
+    builder.commit(&mut window);
+    window.flush();
+
+    assert!(recorder(&window).draw_calls().count() >= 2);
+    assert_eq!(checked_triangles(&window), 2 * QUADS as usize);
+    for (vertices, _, _) in recorder(&window).draw_calls() {
+        for v in vertices {
+            assert_eq!(v.uv.x, v.position.x / QUADS as f32);
+            assert_eq!(v.uv.y, v.position.y);
+        }
+    }
+}
+
+#[test]
+fn keeps_batches_addressable_by_u16_indices() {
+    let mut backend = RecordingBackend::new(100., 100.);
+    backend.max_submission_size = (200_000, usize::MAX);
+    let mut window = Window::new(backend);
+    quads(&window, 10_000).commit(&mut window);
+    quads(&window, 10_000).commit(&mut window);
+    window.flush();
+
+    assert!(recorder(&window).draw_calls().count() >= 2);
+    assert!(
+        recorder(&window)
+            .draw_calls()
+            .all(|(vertices, _, _)| vertices.len() <= u16::MAX as usize + 1)
+    );
+    assert_eq!(checked_triangles(&window), 40_000);
+}
+
+#[test]
+fn submits_at_least_one_triangle_at_a_time() {
+    let mut backend = RecordingBackend::new(100., 100.);
+    backend.max_submission_size = (1, 2);
+    let mut window = Window::new(backend);
+    quads(&window, 3).commit(&mut window);
+    window.fill_rect(
+        &Box2D::new(point(10., 10.), point(20., 20.)),
+        &Transform::identity(),
+        Color::new(1., 0., 0., 1.).into_shading(),
+        1.,
+    );
+    window.flush();
+
+    assert!(
+        recorder(&window)
+            .draw_calls()
+            .all(|(vertices, indices, _)| vertices.len() == 3 && indices.len() == 3)
+    );
+    assert!(checked_triangles(&window) >= 8);
+}
+
+/// A rounded square and a label that never change, counting its renders.
+struct StillScene {
+    color: Color,
+    label: Label,
+    renders: Rc<Cell<u32>>,
+    next: Option<NextScene>,
+}
+
+impl StillScene {
+    fn new(color: Color, renders: Rc<Cell<u32>>) -> Self {
+        Self {
+            color,
+            label: Label::new("still"),
+            renders,
+            next: None,
+        }
+    }
+}
+
+impl Layout for StillScene {
+    fn components(&mut self) -> Vec<(Transform, &mut dyn Component)> {
+        LayoutBuilder::new()
+            .at_rect((50., 80., 1., 1.), &mut self.label)
+            .build()
+    }
+
+    fn before_render(&mut self, tr: &Transform, target: &mut Window) {
+        self.renders.set(self.renders.get() + 1);
+        target.fill_rounded_rect(
+            &Box2D::new(point(30., 30.), point(70., 70.)),
+            &BorderRadii::new(8.),
+            tr,
+            self.color.into_shading(),
+            1.,
+        );
+    }
+
+    fn is_layout_dirty(&mut self) -> bool {
+        self.next.is_some()
+    }
+}
+
+impl Scene for StillScene {
+    fn next_scene(&mut self) -> Option<NextScene> {
+        self.next.take()
+    }
+}
+
+#[test]
+fn replays_a_clean_scene_tree() {
+    let mut window = Window::new(RecordingBackend::new(100., 100.));
+    let renders = Rc::new(Cell::new(0));
+    let mut root = Retained::new(SceneManager::new(StillScene::new(
+        Color::new(1., 0., 0., 1.),
+        renders.clone(),
+    )));
+    let frame = |root: &mut Retained<SceneManager>, window: &mut Window| {
+        root.render(&Transform::identity(), window);
+        window.update();
+        let stats = window.frame_stats();
+        let result = (
+            stats.labels_laid_out,
+            stats.tessellation_time,
+            recorder(window).draw_calls().count(),
+        );
+        window
+            .backend_mut::<RecordingBackend>()
+            .unwrap()
+            .commands
+            .clear();
+        result
+    };
+
+    let (laid_out, tessellation_time, _) = frame(&mut root, &mut window);
+    assert_eq!((laid_out, renders.get()), (1, 1));
+    assert!(tessellation_time > Duration::ZERO);
+    assert!(!root.is_dirty());
+    let (laid_out, tessellation_time, calls) = frame(&mut root, &mut window);
+    assert_eq!((laid_out, tessellation_time), (0, Duration::ZERO));
+    assert!(calls > 0, "replayed the recording");
+    assert_eq!(renders.get(), 1);
+
+    // Scene changes render the tree again
+    let pushed = Rc::new(Cell::new(0));
+    let mut scene = StillScene::new(Color::new(0., 0., 1., 1.), renders.clone());
+    scene.next = Some(NextScene::Push(Box::new(StillScene::new(
+        Color::new(0., 1., 0., 1.),
+        pushed.clone(),
+    ))));
+    root.inner.scene_stack[0] = Box::new(scene);
+    root.inner.mark_changed();
+    assert!(root.is_dirty());
+    frame(&mut root, &mut window);
+    assert!(root.is_dirty(), "the pushed scene is not drawn yet");
+    frame(&mut root, &mut window);
+    assert_eq!(pushed.get(), 1);
+    assert!(!root.is_dirty());
+    frame(&mut root, &mut window);
+    assert_eq!(pushed.get(), 1);
+}
+
+/// Records the touches it gets, filling its unit square.
+#[derive(Default)]
+struct Pad {
+    touches: Vec<(TouchPhase, f32, f32)>,
+}
+
+impl Component for Pad {
+    fn touch(&mut self, touch: &Touch) -> anyhow::Result<bool> {
+        self.touches
+            .push((touch.phase, touch.position.x, touch.position.y));
+        Ok(true)
+    }
+
+    fn render(&mut self, tr: &Transform, target: &mut Window) {
+        Square(Color::new(1., 1., 1., 1.)).render(tr, target);
+    }
+}
+
+/// A pad larger than its clip, in a 100 by 100 layout.
+struct Clipped {
+    clip: Clip,
+    pad: Pad,
+}
+
+impl Clipped {
+    fn new(clip: impl Into<Clip>) -> Self {
+        Self {
+            clip: clip.into(),
+            pad: Pad::default(),
+        }
+    }
+}
+
+impl Layout for Clipped {
+    fn components(&mut self) -> Vec<(Transform, &mut dyn Component)> {
+        LayoutBuilder::new()
+            .at_rect((50., 50., 100., 100.), &mut self.pad)
+            .build()
+    }
+
+    fn clip(&self) -> Option<Clip> {
+        Some(self.clip.clone())
+    }
+}
+
+fn touch(phase: TouchPhase, x: f32, y: f32) -> Touch {
+    Touch {
+        id: 0,
+        phase,
+        position: vec2(x, y),
+    }
+}
+
+#[test]
+fn drops_touches_starting_outside_the_clip() {
+    let mut layout = Clipped::new(Box2D::new(point(20., 20.), point(80., 80.)));
+    assert!(!layout.touch(&touch(TouchPhase::Started, 10., 50.)).unwrap());
+    assert!(layout.pad.touches.is_empty());
+
+    assert!(layout.touch(&touch(TouchPhase::Started, 50., 50.)).unwrap());
+    // Touches that started inside can move out and end there
+    assert!(layout.touch(&touch(TouchPhase::Moved, 90., 50.)).unwrap());
+    assert!(layout.touch(&touch(TouchPhase::Ended, 95., 50.)).unwrap());
+    let phases = layout.pad.touches.iter().map(|t| t.0).collect::<Vec<_>>();
+    assert_eq!(
+        phases,
+        [TouchPhase::Started, TouchPhase::Moved, TouchPhase::Ended]
+    );
+    // In the coordinate system of the pad
+    assert_eq!(layout.pad.touches[0], (TouchPhase::Started, 0., 0.));
+
+    // Round clips are hit tested exactly
+    let mut builder = lyon::path::Path::builder();
+    builder.add_circle(point(50., 50.), 30., lyon::path::Winding::Positive);
+    let mut layout = Clipped::new(builder.build());
+    assert!(!layout.touch(&touch(TouchPhase::Started, 25., 25.)).unwrap());
+    assert!(layout.touch(&touch(TouchPhase::Started, 70., 50.)).unwrap());
+}
+
+/// The stencil and scissor commands of rendering `layout` with `tr`.
+fn clip_commands(layout: &mut Clipped, tr: &Transform) -> Vec<Command> {
+    let mut window = Window::new(RecordingBackend::new(200., 200.));
+    layout.render(tr, &mut window);
+    window.flush();
+    window
+        .backend_mut::<RecordingBackend>()
+        .unwrap()
+        .take_commands()
+        .into_iter()
+        .filter(|command| {
+            matches!(
+                command,
+                Command::SetScissor(_) | Command::PushStencil { .. } | Command::PopStencil { .. }
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn clips_axis_aligned_rectangles_with_the_scissor_alone() {
+    let mut layout = Clipped::new(Box2D::new(point(20., 20.), point(80., 80.)));
+    let tr = Transform::new_translation(&nalgebra::Vector2::new(10., 0.))
+        * Transform::new_nonuniform_scaling(&nalgebra::Vector2::new(2., -1.));
+    let commands = clip_commands(&mut layout, &tr);
+    let [Command::SetScissor(Some(rect)), Command::SetScissor(None)] = commands.as_slice() else {
+        panic!("{commands:?}");
+    };
+    assert_eq!((rect.x, rect.y, rect.w, rect.h), (50., -80., 120., 60.));
+}
+
+#[test]
+fn clips_other_shapes_with_the_stencil() {
+    let rotated = Transform::new_translation(&nalgebra::Vector2::new(100., 100.))
+        * nalgebra::Rotation2::new(0.3).to_homogeneous()
+        * Transform::new_translation(&nalgebra::Vector2::new(-50., -50.));
+    let mut builder = lyon::path::Path::builder();
+    builder.add_rounded_rectangle(
+        &Box2D::new(point(20., 20.), point(80., 80.)),
+        &BorderRadii::new(10.),
+        lyon::path::Winding::Positive,
+    );
+    for (clip, tr) in [
+        (
+            Clip::Rect(Box2D::new(point(20., 20.), point(80., 80.))),
+            rotated,
+        ),
+        (Clip::Path(builder.build()), Transform::identity()),
+    ] {
+        let commands = clip_commands(&mut Clipped::new(clip), &tr);
+        let [
+            Command::SetScissor(Some(_)),
+            Command::PushStencil { vertices, .. },
+            Command::PopStencil { .. },
+            Command::SetScissor(None),
+        ] = commands.as_slice()
+        else {
+            panic!("{commands:?}");
+        };
+        assert!(vertices.len() >= 4);
+    }
+}
+
+#[test]
+fn clips_everything_to_an_empty_path() {
+    let mut layout = Clipped::new(lyon::path::Path::new());
+    let commands = clip_commands(&mut layout, &Transform::identity());
+    let [Command::SetScissor(Some(rect)), Command::SetScissor(None)] = commands.as_slice() else {
+        panic!("{commands:?}");
+    };
+    assert_eq!((rect.w, rect.h), (0., 0.));
+}